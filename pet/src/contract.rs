@@ -1,37 +1,136 @@
-use crate::msg::{FoodHandleMsg, HandleMsg, InitMsg, QueryMsg, QueryResponse};
-use crate::state::{config, config_read, State};
+use crate::msg::{
+    Board, Coin, Extension, FoodHandleMsg, Game, HandleAnswer, HandleMsg, InitMsg,
+    LeaderboardEntry, PetInfo, PetStatus, QueryAnswer, QueryMsg, ReceiveMsg,
+    ResponseStatus::Success, Snip721HandleMsg, Snip721ReceiveMsg, Trait, Wellbeing,
+};
+use crate::rand::{sha_256, Prng};
+use crate::state::{
+    add_child, add_owner_pet, config, config_read, find_accessory, find_food, get_feed_events,
+    get_illnesses, get_memorials, get_owner_memorials, get_receiver_hash, load_pet,
+    read_accessories, read_banned_words, read_caretakers, read_children, read_foods,
+    read_held_food, read_leaderboard, read_owner_pets, read_viewing_key, remove_owner_pet,
+    save_pet, set_receiver_hash, store_feed_event, store_illness, store_memorial,
+    update_leaderboard, write_accessories, write_banned_words, write_caretakers, write_foods,
+    write_held_food, write_viewing_key, Accessory, Caretaker, Config, DeathCause, FeedEvent, Food,
+    Hibernation, Illness, IllnessOutcome, Item, LifeStage, Medicine, Memorial, StatKind, State,
+    TokenStandard, MAX_DECAY_PERCENT, MAX_STAT,
+};
+use crate::viewing_key::{ViewingKey, VIEWING_KEY_SIZE};
 use cosmwasm_std::{
-    to_binary, Api, Binary, Env, Extern, HandleResponse, InitResponse, Querier, StdError,
-    StdResult, Storage,
+    from_binary, log, to_binary, Api, Binary, CosmosMsg, Env, Extern, HandleResponse, HumanAddr,
+    InitResponse, Querier, ReadonlyStorage, StdError, StdResult, Storage, Uint128,
 };
 use secret_toolkit::utils::HandleCallback;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-const FOUR_HOURS: u128 = Duration::from_secs(4 * 60 * 60).as_millis();
+/// Countdown clock granularity is the block time, which is in seconds.
+const FOUR_HOURS: u64 = 4 * 60 * 60;
+
+const DEFAULT_STARVATION_WINDOW: u64 = FOUR_HOURS;
+const DEFAULT_TIME_PER_FOOD: u64 = FOUR_HOURS;
+const DEFAULT_MAX_SATIATION: u64 = FOUR_HOURS;
+const DEFAULT_HAPPINESS_DECAY: u64 = 4;
+const DEFAULT_CLEANLINESS_DECAY: u64 = 3;
+const DEFAULT_ENERGY_DECAY: u64 = 5;
+const DEFAULT_REVIVAL_MULTIPLIER: u64 = 3;
+const DEFAULT_HIBERNATION_FEE: u128 = 1;
+const DEFAULT_MAX_HIBERNATION: u64 = 3 * 24 * 60 * 60;
+const DEFAULT_BREEDING_FEE: u128 = 10;
+
+/// Chance (out of 256) of each gene mutating when pets are bred.
+const MUTATION_CHANCE: u8 = 8;
+
+/// Chance (out of 256) of a pet falling ill when it's fed or played with.
+const ILLNESS_CHANCE: u8 = 3;
+const NEGLECTED_ILLNESS_CHANCE: u8 = 48;
+/// Pet is neglected when it's fed in the last tenth of the max satiation, or is this dirty.
+const NEGLECTED_SATIATION_FRACTION: u64 = 10;
+const NEGLECTED_CLEANLINESS: u64 = 20;
+
+/// Number of spots a pet can hide in when playing hide-and-seek.
+const HIDING_SPOTS: u8 = 4;
+/// Won games pay the wager back times this.
+const COIN_FLIP_PAYOUT: u128 = 2;
+const HIDE_AND_SEEK_PAYOUT: u128 = 3;
+
+/// Number of different items a pet can hold.
+const MAX_INVENTORY: usize = 20;
+
+/// Boards listed pets are ranked on.
+const BOARDS: [Board; 4] = [Board::Age, Board::Lifespan, Board::FoodEaten, Board::Streak];
+const MAX_LEADERBOARD_PAGE: u32 = 50;
+
+/// Longest bio and avatar URI in bytes a pet can have.
+const MAX_BIO_LEN: usize = 280;
+const MAX_AVATAR_URI_LEN: usize = 256;
+
+/// Happiness gained and energy spent playing with a pet.
+const PLAY_HAPPINESS: u64 = 25;
+const PLAY_ENERGY: u64 = 10;
 
 impl HandleCallback for FoodHandleMsg {
     const BLOCK_SIZE: usize = 256;
 }
 
+impl HandleCallback for Snip721ReceiveMsg {
+    const BLOCK_SIZE: usize = 256;
+}
+
+impl HandleCallback for Snip721HandleMsg {
+    const BLOCK_SIZE: usize = 256;
+}
+
 pub fn init<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
     msg: InitMsg,
 ) -> StdResult<InitResponse> {
-    let since_epoch = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap_or_default();
+    let prng_seed_hashed = sha_256(&msg.prng_seed.0);
 
-    let state = State {
-        starved: false,
-        food_token_code_hash: msg.food_token_code_hash,
-        food_token_addr: msg.food_token_addr,
-        full_until: since_epoch.as_millis() + FOUR_HOURS,
-        owner: env.message.sender.clone(),
+    let config_state = Config {
+        admin: msg.admin.unwrap_or_else(|| env.message.sender.clone()),
+        food_token_code_hash: msg.food_token_code_hash.clone(),
+        food_token_addr: msg.food_token_addr.clone(),
+        starvation_window: msg.starvation_window.unwrap_or(DEFAULT_STARVATION_WINDOW),
+        time_per_food: msg.time_per_food.unwrap_or(DEFAULT_TIME_PER_FOOD),
+        max_satiation: msg.max_satiation.unwrap_or(DEFAULT_MAX_SATIATION),
+        happiness_decay: msg.happiness_decay.unwrap_or(DEFAULT_HAPPINESS_DECAY),
+        cleanliness_decay: msg.cleanliness_decay.unwrap_or(DEFAULT_CLEANLINESS_DECAY),
+        energy_decay: msg.energy_decay.unwrap_or(DEFAULT_ENERGY_DECAY),
+        revival_enabled: msg.revival_enabled.unwrap_or(true),
+        revival_multiplier: msg.revival_multiplier.unwrap_or(DEFAULT_REVIVAL_MULTIPLIER),
+        settle_bounty: msg.settle_bounty.unwrap_or_else(Uint128::zero),
+        pool: Uint128::zero(),
+        hibernation_fee: msg
+            .hibernation_fee
+            .unwrap_or(Uint128(DEFAULT_HIBERNATION_FEE)),
+        max_hibernation: msg.max_hibernation.unwrap_or(DEFAULT_MAX_HIBERNATION),
+        breeding_fee: msg.breeding_fee.unwrap_or(Uint128(DEFAULT_BREEDING_FEE)),
+        medicine: None,
+        pet_count: 0,
+        prng_seed: prng_seed_hashed.to_vec(),
+        prng_nonce: 0,
     };
 
-    config(&mut deps.storage).save(&state)?;
-    Ok(InitResponse::default())
+    check_feeding_config(&config_state)?;
+    config(&mut deps.storage).save(&config_state)?;
+    if let Some(words) = msg.banned_words {
+        write_banned_words(&mut deps.storage, &words)?;
+    }
+
+    // Pet is fed by sending FOOD to it, so FOOD contract
+    // has to know where to deliver the Receive callback.
+    let register_msg = FoodHandleMsg::RegisterReceive {
+        code_hash: env.contract_code_hash,
+        padding: None,
+    };
+
+    let cosmos_msg =
+        register_msg.to_cosmos_msg(msg.food_token_code_hash, msg.food_token_addr, None)?;
+
+    Ok(InitResponse {
+        messages: vec![cosmos_msg],
+        log: vec![],
+    })
 }
 
 pub fn handle<S: Storage, A: Api, Q: Querier>(
@@ -40,101 +139,5255 @@ pub fn handle<S: Storage, A: Api, Q: Querier>(
     msg: HandleMsg,
 ) -> StdResult<HandleResponse> {
     match msg {
-        HandleMsg::Feed {} => try_feed(deps, env),
+        HandleMsg::Receive {
+            from, amount, msg, ..
+        } => try_receive(deps, env, from, amount, msg),
+        HandleMsg::ReceiveNft {
+            sender,
+            token_id,
+            msg,
+        } => try_receive_nft(deps, env, sender, token_id, msg),
+        HandleMsg::CreatePet { name } => try_create_pet(deps, env, name),
+        HandleMsg::TransferOwnership { pet_id, new_owner } => {
+            try_transfer_ownership(deps, env, pet_id, new_owner)
+        }
+        HandleMsg::AcceptOwnership { pet_id } => try_accept_ownership(deps, env, pet_id),
+        HandleMsg::CancelOwnershipTransfer { pet_id } => {
+            try_cancel_ownership_transfer(deps, env, pet_id)
+        }
+        HandleMsg::Settle { pet_id } => try_settle(deps, env, pet_id),
+        HandleMsg::Wake { pet_id } => try_wake(deps, env, pet_id),
+        HandleMsg::ApproveBreeding { pet_id, partner_id } => {
+            try_approve_breeding(deps, env, pet_id, partner_id)
+        }
+        HandleMsg::Play { pet_id, game } => {
+            let player = env.message.sender.clone();
+            try_play(deps, env, player, pet_id, game, Uint128::zero())
+        }
+        HandleMsg::Clean { pet_id } => try_clean(deps, env, pet_id),
+        HandleMsg::Sleep { pet_id } => try_sleep(deps, env, pet_id),
+        HandleMsg::WithdrawEscrow { pet_id, amount, .. } => {
+            try_withdraw_escrow(deps, env, pet_id, amount)
+        }
+        HandleMsg::Equip {
+            pet_id,
+            contract,
+            token_id,
+        } => try_equip(deps, env, pet_id, contract, token_id),
+        HandleMsg::Unequip {
+            pet_id,
+            contract,
+            token_id,
+        } => try_unequip(deps, env, pet_id, contract, token_id),
+        HandleMsg::SetMetadata {
+            pet_id,
+            name,
+            bio,
+            avatar_uri,
+            ..
+        } => try_set_metadata(deps, env, pet_id, name, bio, avatar_uri),
+        HandleMsg::SetListing { pet_id, public, .. } => try_set_listing(deps, env, pet_id, public),
+        HandleMsg::AddCaretaker {
+            pet_id,
+            address,
+            expiration,
+        } => try_add_caretaker(deps, env, pet_id, address, expiration),
+        HandleMsg::RemoveCaretaker { pet_id, address } => {
+            try_remove_caretaker(deps, env, pet_id, address)
+        }
+        HandleMsg::TransferNft {
+            recipient,
+            token_id,
+            ..
+        } => try_transfer_nft(deps, env, recipient, token_id),
+        HandleMsg::SendNft {
+            contract,
+            token_id,
+            msg,
+            ..
+        } => try_send_nft(deps, env, contract, token_id, msg),
+        HandleMsg::RegisterReceiveNft { code_hash, .. } => {
+            try_register_receive_nft(deps, env, code_hash)
+        }
+        HandleMsg::CreateViewingKey { entropy, .. } => try_create_key(deps, env, entropy),
+        HandleMsg::SetViewingKey { key, .. } => try_set_key(deps, env, key),
+
+        // Admin
+        HandleMsg::SetFeedingConfig {
+            starvation_window,
+            time_per_food,
+            max_satiation,
+        } => set_feeding_config(deps, env, starvation_window, time_per_food, max_satiation),
+        HandleMsg::SetDecayConfig {
+            happiness_decay,
+            cleanliness_decay,
+            energy_decay,
+        } => set_decay_config(deps, env, happiness_decay, cleanliness_decay, energy_decay),
+        HandleMsg::SetRevivalConfig {
+            enabled,
+            multiplier,
+        } => set_revival_config(deps, env, enabled, multiplier),
+        HandleMsg::SetSettleBounty { bounty } => set_settle_bounty(deps, env, bounty),
+        HandleMsg::SetHibernationConfig { fee, max_duration } => {
+            set_hibernation_config(deps, env, fee, max_duration)
+        }
+        HandleMsg::SetBreedingFee { fee } => set_breeding_fee(deps, env, fee),
+        HandleMsg::SetFood {
+            contract,
+            code_hash,
+            nutrition,
+            burn,
+        } => set_food(deps, env, contract, code_hash, nutrition, burn),
+        HandleMsg::RemoveFood { contract } => remove_food(deps, env, contract),
+        HandleMsg::WithdrawHeldFood {
+            contract,
+            code_hash,
+            recipient,
+            amount,
+        } => withdraw_held_food(deps, env, contract, code_hash, recipient, amount),
+        HandleMsg::SetMedicine {
+            contract,
+            code_hash,
+        } => set_medicine(deps, env, contract, code_hash),
+        HandleMsg::SetAccessory {
+            contract,
+            code_hash,
+            standard,
+            stat,
+            decay_percent,
+        } => set_accessory(
+            deps,
+            env,
+            contract,
+            code_hash,
+            standard,
+            stat,
+            decay_percent,
+        ),
+        HandleMsg::RemoveAccessory { contract } => remove_accessory(deps, env, contract),
+        HandleMsg::SetBannedWords { words } => set_banned_words(deps, env, words),
+    }
+}
+
+pub fn try_receive<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    from: HumanAddr,
+    amount: Uint128,
+    msg: Option<Binary>,
+) -> StdResult<HandleResponse> {
+    let receive_msg = match msg {
+        Some(msg) => from_binary(&msg)?,
+        None => {
+            return Err(StdError::GenericErr {
+                msg: "Receive message is missing".to_string(),
+                backtrace: None,
+            })
+        }
+    };
+
+    // Anyone can call Receive directly, so only callbacks
+    // coming from the medicine or a whitelisted food contract are trusted.
+    let config_state = config_read(&deps.storage).load()?;
+    if let Some(medicine) = config_state.medicine {
+        if medicine.contract == env.message.sender {
+            return match receive_msg {
+                ReceiveMsg::Treat { pet_id } => {
+                    try_treat(deps, env, medicine, from, amount, pet_id)
+                }
+                _ => Err(StdError::GenericErr {
+                    msg: "Medicine can only be used to treat pets".to_string(),
+                    backtrace: None,
+                }),
+            };
+        }
+    }
+
+    if let Some(accessory) = find_accessory(&deps.storage, &env.message.sender)? {
+        return match receive_msg {
+            ReceiveMsg::AddItem { pet_id } if accessory.standard == TokenStandard::Snip20 => {
+                try_add_item(deps, env, from, accessory, pet_id, None, amount)
+            }
+            _ => Err(StdError::GenericErr {
+                msg: "Accessories can only be added to a pet's inventory".to_string(),
+                backtrace: None,
+            }),
+        };
+    }
+
+    let food = match find_food(&deps.storage, &env.message.sender)? {
+        Some(food) => food,
+        None => {
+            return Err(StdError::GenericErr {
+                msg: "Only FOOD tokens are accepted".to_string(),
+                backtrace: None,
+            })
+        }
+    };
+
+    match receive_msg {
+        ReceiveMsg::Feed { pet_id } => try_feed(deps, env, food, from, amount, pet_id),
+        ReceiveMsg::Revive { pet_id } => try_revive(deps, env, food, from, amount, pet_id),
+        ReceiveMsg::FundPool {} => try_fund_pool(deps, food, amount),
+        ReceiveMsg::Hibernate { pet_id, until } => {
+            try_hibernate(deps, env, food, from, amount, pet_id, until)
+        }
+        ReceiveMsg::Breed {
+            pet_id,
+            partner_id,
+            name,
+        } => try_breed(deps, env, food, from, amount, (pet_id, partner_id), name),
+        ReceiveMsg::DepositEscrow { pet_id } => {
+            try_deposit_escrow(deps, env, food, from, amount, pet_id)
+        }
+        ReceiveMsg::Play { pet_id, game } => {
+            let config_state = config_read(&deps.storage).load()?;
+            if food.contract != config_state.food_token_addr {
+                return Err(StdError::GenericErr {
+                    msg: "Wagers are made with FOOD".to_string(),
+                    backtrace: None,
+                });
+            }
+            try_play(deps, env, from, pet_id, Some(game), amount)
+        }
+        ReceiveMsg::Treat { .. } => Err(StdError::GenericErr {
+            msg: "Pets can only be treated with medicine".to_string(),
+            backtrace: None,
+        }),
+        ReceiveMsg::AddItem { .. } => Err(StdError::GenericErr {
+            msg: "Only accessories can be added to a pet's inventory".to_string(),
+            backtrace: None,
+        }),
+    }
+}
+
+pub fn try_receive_nft<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    sender: HumanAddr,
+    token_id: String,
+    msg: Option<Binary>,
+) -> StdResult<HandleResponse> {
+    // Same as with Receive, only whitelisted contracts are trusted
+    let accessory = match find_accessory(&deps.storage, &env.message.sender)? {
+        Some(accessory) if accessory.standard == TokenStandard::Snip721 => accessory,
+        _ => {
+            return Err(StdError::GenericErr {
+                msg: "Only accessory NFTs are accepted".to_string(),
+                backtrace: None,
+            })
+        }
+    };
+
+    let receive_msg = match msg {
+        Some(msg) => from_binary(&msg)?,
+        None => {
+            return Err(StdError::GenericErr {
+                msg: "Receive message is missing".to_string(),
+                backtrace: None,
+            })
+        }
+    };
+    match receive_msg {
+        ReceiveMsg::AddItem { pet_id } => try_add_item(
+            deps,
+            env,
+            sender,
+            accessory,
+            pet_id,
+            Some(token_id),
+            Uint128(1),
+        ),
+        _ => Err(StdError::GenericErr {
+            msg: "Accessories can only be added to a pet's inventory".to_string(),
+            backtrace: None,
+        }),
+    }
+}
+
+pub fn try_create_pet<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    name: String,
+) -> StdResult<HandleResponse> {
+    check_name(&deps.storage, &name)?;
+
+    let mut config_state = config_read(&deps.storage).load()?;
+    config_state.pet_count += 1;
+    let pet_id = config_state.pet_count;
+    let dna = pet_prng(&mut config_state, &env, &env.message.sender, pet_id).rand_bytes();
+    config(&mut deps.storage).save(&config_state)?;
+
+    let pet = State::new(
+        pet_id,
+        name,
+        env.message.sender.clone(),
+        env.block.time,
+        config_state.starvation_window,
+        Binary(dna.to_vec()),
+    );
+    save_pet(&mut deps.storage, &pet)?;
+
+    let owner = deps.api.canonical_address(&env.message.sender)?;
+    add_owner_pet(&mut deps.storage, &owner, pet_id)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![log("action", "create_pet"), log("pet_id", pet_id)],
+        data: Some(to_binary(&HandleAnswer::CreatePet { pet_id })?),
+    })
+}
+
+/// Pet is neglected if it's close to starving or dirty, which makes it more likely to fall ill.
+fn is_neglected(config_state: &Config, pet: &State, now: u64) -> bool {
+    let cleanliness = pet.cleanliness.value_at(
+        pet.stats_time(now),
+        stat_decay(config_state, pet, StatKind::Cleanliness),
+    );
+    let max_satiation = pet.traits.satiation_time(config_state.max_satiation);
+    pet.seconds_left(now) * NEGLECTED_SATIATION_FRACTION < max_satiation
+        || cleanliness < NEGLECTED_CLEANLINESS
+}
+
+/// Makes a healthy pet fall ill if the random `roll` is below the chance, returns whether it did.
+fn roll_illness(pet: &mut State, now: u64, neglected: bool, roll: u8) -> bool {
+    if pet.ill_since.is_some() {
+        return false;
+    }
+
+    let chance = if neglected {
+        NEGLECTED_ILLNESS_CHANCE
+    } else {
+        ILLNESS_CHANCE
+    };
+    if roll < chance {
+        pet.fall_ill(now);
     }
+    roll < chance
+}
+
+/// Random generator seeded with the contract's secret seed, a nonce and data of the current block.
+/// `player` is whoever acts on the pet, not the token contract which forwarded a Receive.
+/// Bumps the nonce, so the config has to be saved afterwards.
+fn pet_prng(config_state: &mut Config, env: &Env, player: &HumanAddr, pet_id: u64) -> Prng {
+    // Otherwise a free roll would reveal the outcome of a wagered one in the same block
+    config_state.prng_nonce = config_state.prng_nonce.wrapping_add(1);
+    let mut entropy = config_state.prng_nonce.to_be_bytes().to_vec();
+    entropy.extend_from_slice(&env.block.height.to_be_bytes());
+    entropy.extend_from_slice(&env.block.time.to_be_bytes());
+    entropy.extend_from_slice(player.0.as_bytes());
+    entropy.extend_from_slice(&pet_id.to_be_bytes());
+
+    Prng::new(&config_state.prng_seed, &entropy)
 }
 
 pub fn try_feed<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
+    food: Food,
+    from: HumanAddr,
+    amount: Uint128,
+    pet_id: u64,
 ) -> StdResult<HandleResponse> {
-    if env.message.sent_funds.len() != 1 {
+    if amount.u128() < 1 {
+        return Err(StdError::GenericErr {
+            msg: "Invalid amount (<0)".to_string(),
+            backtrace: None,
+        });
+    }
+    let now = env.block.time;
+
+    let mut config_state = config_read(&deps.storage).load()?;
+    let mut pet = load_pet(&deps.storage, pet_id)?;
+    if !can_feed(&deps.storage, &pet, &from, now)? {
+        return Err(StdError::Unauthorized { backtrace: None });
+    }
+    let mut messages = apply_escrow(&mut deps.storage, &config_state, &mut pet, now)?;
+    if pet.is_hibernating(now) {
         return Err(StdError::GenericErr {
-            msg: "No funds or multiple sent funds".to_string(),
+            msg: format!("Pet {} is hibernating", pet_id),
             backtrace: None,
         });
     }
-    let sent_funds = &env.message.sent_funds[0];
-    if sent_funds.denom != "FOOD" {
+
+    let event = FeedEvent {
+        time: now,
+        feeder: from.clone(),
+        food: food.contract.clone(),
+        amount,
+        refunded: !pet.is_alive(now),
+    };
+    store_feed_event(&mut deps.storage, pet_id, &event.into_stored(&deps.api)?)?;
+
+    // Dead pet can't eat, so food goes back to whoever sent it.
+    if !pet.is_alive(now) {
+        record_death(deps, &config_state, &mut pet, now)?;
+        save_pet(&mut deps.storage, &pet)?;
+
+        let transfer_msg = FoodHandleMsg::Transfer {
+            recipient: from,
+            amount,
+            padding: None,
+        };
+
+        messages.push(transfer_msg.to_cosmos_msg(food.code_hash, food.contract, None)?);
+
+        return Ok(HandleResponse {
+            messages,
+            log: vec![
+                log("action", "refund"),
+                log("pet_id", pet_id),
+                log("amount", amount),
+            ],
+            data: None,
+        });
+    }
+
+    let neglected = is_neglected(&config_state, &pet, now);
+    pet.wake(now);
+
+    // Every food unit buys some time, but pet can't be fed too far ahead.
+    // Lowering the cap later doesn't take away time pet already has.
+    let nutrition = pet.traits.satiation_time(food.nutrition);
+    let gained = amount
+        .u128()
+        .saturating_mul(nutrition as u128)
+        .saturating_mul(feeding_efficiency(&config_state, &pet, now) as u128)
+        / 100;
+    let max_satiation = pet.traits.satiation_time(config_state.max_satiation);
+    let max_full_until = (now + max_satiation) as u128;
+    let full_until = (pet.full_until as u128)
+        .saturating_add(gained)
+        .min(max_full_until) as u64;
+    pet.full_until = full_until.max(pet.full_until);
+    pet.food_eaten = Uint128(pet.food_eaten.u128().saturating_add(amount.u128()));
+    pet.feed_count += 1;
+    pet.hibernated_since_fed = false;
+    pet.record_feed(now);
+
+    let mut logs = vec![
+        log("action", "feed"),
+        log("pet_id", pet_id),
+        log("amount", amount),
+    ];
+
+    let stage = pet.stage_at(now);
+    if stage != pet.stage {
+        pet.stage = stage;
+        logs.push(log("stage", stage.as_str()));
+    }
+    let roll = pet_prng(&mut config_state, &env, &from, pet_id).rand_bytes()[0];
+    config(&mut deps.storage).save(&config_state)?;
+    if roll_illness(&mut pet, now, neglected, roll) {
+        logs.push(log("illness", "fell_ill"));
+    }
+    update_leaderboards(&mut deps.storage, &pet, now)?;
+    save_pet(&mut deps.storage, &pet)?;
+
+    // Food was already transferred to the Pet by `Send`,
+    // so Pet burns it from its own balance, or keeps it until the admin withdraws it.
+    if food.burn {
+        let burn_msg = FoodHandleMsg::Burn {
+            amount,
+            padding: None,
+        };
+        messages.push(burn_msg.to_cosmos_msg(food.code_hash, food.contract, None)?);
+    } else {
+        let held = read_held_food(&deps.storage, &food.contract)?;
+        let held = Uint128(held.u128().saturating_add(amount.u128()));
+        write_held_food(&mut deps.storage, &food.contract, held)?;
+    }
+
+    Ok(HandleResponse {
+        messages,
+        log: logs,
+        data: None,
+    })
+}
+
+pub fn try_revive<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    food: Food,
+    from: HumanAddr,
+    amount: Uint128,
+    pet_id: u64,
+) -> StdResult<HandleResponse> {
+    let now = env.block.time;
+
+    let config_state = config_read(&deps.storage).load()?;
+    if !config_state.revival_enabled {
         return Err(StdError::GenericErr {
-            msg: "Invalid denomination".to_string(),
+            msg: "Revival is disabled".to_string(),
             backtrace: None,
         });
     }
-    if sent_funds.amount.u128() < 1 {
+    if food.contract != config_state.food_token_addr {
         return Err(StdError::GenericErr {
-            msg: "Invalid amount (<0)".to_string(),
+            msg: "Pets can only be revived with FOOD".to_string(),
             backtrace: None,
         });
     }
-    let since_epoch = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_millis();
 
-    let state = config(&mut deps.storage).load()?;
-    if state.starved {
+    let mut pet = load_pet(&deps.storage, pet_id)?;
+    if from != pet.owner {
+        return Err(StdError::Unauthorized { backtrace: None });
+    }
+    let mut messages = apply_escrow(&mut deps.storage, &config_state, &mut pet, now)?;
+    if pet.is_alive(now) {
         return Err(StdError::GenericErr {
-            msg: "dead".to_string(),
+            msg: format!("Pet {} is alive", pet_id),
             backtrace: None,
         });
     }
 
-    let mut starved = false;
-    config(&mut deps.storage).update(|mut state| {
-        if env.message.sender != state.owner {
-            return Err(StdError::Unauthorized { backtrace: None });
-        }
+    let cost = revival_cost(&config_state);
+    if amount != cost {
+        return Err(StdError::GenericErr {
+            msg: format!("Reviving a pet costs {} FOOD", cost),
+            backtrace: None,
+        });
+    }
 
-        if state.full_until < since_epoch {
-            state.starved = true;
-            starved = true;
-        } else {
-            state.full_until = since_epoch + FOUR_HOURS;
-        }
-        Ok(state)
-    })?;
+    record_death(deps, &config_state, &mut pet, now)?;
+    pet.revive(now, config_state.starvation_window);
+    update_leaderboards(&mut deps.storage, &pet, now)?;
+    save_pet(&mut deps.storage, &pet)?;
+
+    // Same as feeding, FOOD is already in the Pet's balance
+    let burn_msg = FoodHandleMsg::Burn {
+        amount,
+        padding: None,
+    };
+    messages.push(burn_msg.to_cosmos_msg(food.code_hash, food.contract, None)?);
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![
+            log("action", "revive"),
+            log("pet_id", pet_id),
+            log("amount", amount),
+            log("deaths", pet.deaths),
+        ],
+        data: None,
+    })
+}
+
+pub fn try_fund_pool<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    food: Food,
+    amount: Uint128,
+) -> StdResult<HandleResponse> {
+    let mut config_state = config_read(&deps.storage).load()?;
+    if food.contract != config_state.food_token_addr {
+        return Err(StdError::GenericErr {
+            msg: "Only FOOD can be added to the pool".to_string(),
+            backtrace: None,
+        });
+    }
+
+    // FOOD stays in the Pet's balance until it's paid out
+    config_state.pool = Uint128(config_state.pool.u128().saturating_add(amount.u128()));
+    config(&mut deps.storage).save(&config_state)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![log("action", "fund_pool"), log("amount", amount)],
+        data: None,
+    })
+}
+
+pub fn try_treat<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    medicine: Medicine,
+    from: HumanAddr,
+    amount: Uint128,
+    pet_id: u64,
+) -> StdResult<HandleResponse> {
+    let now = env.block.time;
+
+    if amount != Uint128(1) {
+        return Err(StdError::GenericErr {
+            msg: "Treating a pet takes 1 medicine".to_string(),
+            backtrace: None,
+        });
+    }
+
+    let config_state = config_read(&deps.storage).load()?;
+    let mut pet = load_pet(&deps.storage, pet_id)?;
+    if !can_feed(&deps.storage, &pet, &from, now)? {
+        return Err(StdError::Unauthorized { backtrace: None });
+    }
+    let mut messages = apply_escrow(&mut deps.storage, &config_state, &mut pet, now)?;
+    if !pet.is_alive(now) {
+        return Err(StdError::GenericErr {
+            msg: format!("Pet {} is dead", pet_id),
+            backtrace: None,
+        });
+    }
 
-    if starved {
+    // Treatment would end hibernation early, and the time paid for would be lost
+    if pet.is_hibernating(now) {
         return Err(StdError::GenericErr {
-            msg: "died".to_string(),
+            msg: format!("Pet {} is hibernating", pet_id),
             backtrace: None,
         });
     }
 
-    let burn_from_msg = FoodHandleMsg::BurnFrom {
-        owner: env.message.sender.clone(),
-        amount: sent_funds.amount,
+    let since = match pet.treat(now) {
+        Some(since) => since,
+        None => {
+            return Err(StdError::GenericErr {
+                msg: format!("Pet {} is not ill", pet_id),
+                backtrace: None,
+            })
+        }
+    };
+    let illness = Illness {
+        since,
+        until: now,
+        outcome: IllnessOutcome::Treated,
+    };
+    store_illness(&mut deps.storage, pet_id, &illness)?;
+    save_pet(&mut deps.storage, &pet)?;
+
+    let burn_msg = FoodHandleMsg::Burn {
+        amount,
         padding: None,
     };
+    messages.push(burn_msg.to_cosmos_msg(medicine.code_hash, medicine.contract, None)?);
 
-    let cosmos_msg =
-        burn_from_msg.to_cosmos_msg(state.food_token_code_hash, state.food_token_addr, None)?;
+    Ok(HandleResponse {
+        messages,
+        log: vec![
+            log("action", "treat"),
+            log("pet_id", pet_id),
+            log("ill_since", since),
+        ],
+        data: None,
+    })
+}
+
+/// Debits the pet's escrow for the times its countdown ran out, returning the message
+/// which burns the FOOD used. Callers have to save the pet.
+fn apply_escrow<S: Storage>(
+    storage: &mut S,
+    config_state: &Config,
+    pet: &mut State,
+    now: u64,
+) -> StdResult<Vec<CosmosMsg>> {
+    let used = debit_escrow(config_state, pet, now);
+    if used == 0 {
+        return Ok(vec![]);
+    }
+    update_leaderboards(storage, pet, now)?;
+
+    let burn_msg = FoodHandleMsg::Burn {
+        amount: Uint128(used),
+        padding: None,
+    };
+    Ok(vec![burn_msg.to_cosmos_msg(
+        config_state.food_token_code_hash.clone(),
+        config_state.food_token_addr.clone(),
+        None,
+    )?])
+}
+
+/// Debits the escrow of a pet without burning the FOOD, returns the FOOD used.
+fn debit_escrow(config_state: &Config, pet: &mut State, now: u64) -> u128 {
+    let time_per_food = config_state.time_per_food.min(config_state.max_satiation);
+    pet.apply_escrow(now, pet.traits.satiation_time(time_per_food))
+}
+
+pub fn try_deposit_escrow<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    food: Food,
+    from: HumanAddr,
+    amount: Uint128,
+    pet_id: u64,
+) -> StdResult<HandleResponse> {
+    let now = env.block.time;
+
+    let config_state = config_read(&deps.storage).load()?;
+    if food.contract != config_state.food_token_addr {
+        return Err(StdError::GenericErr {
+            msg: "Escrow can only be funded with FOOD".to_string(),
+            backtrace: None,
+        });
+    }
+
+    // Only the owner funds the escrow, so all of it can be returned to them
+    let mut pet = load_pet(&deps.storage, pet_id)?;
+    if from != pet.owner {
+        return Err(StdError::Unauthorized { backtrace: None });
+    }
+    let messages = apply_escrow(&mut deps.storage, &config_state, &mut pet, now)?;
+    if !pet.is_alive(now) {
+        return Err(StdError::GenericErr {
+            msg: format!("Pet {} is dead", pet_id),
+            backtrace: None,
+        });
+    }
+
+    // FOOD stays in the Pet's balance until the pet eats it, or the owner withdraws it
+    pet.escrow = Uint128(pet.escrow.u128().saturating_add(amount.u128()));
+    save_pet(&mut deps.storage, &pet)?;
 
     Ok(HandleResponse {
-        messages: vec![cosmos_msg],
-        log: vec![],
+        messages,
+        log: vec![
+            log("action", "deposit_escrow"),
+            log("pet_id", pet_id),
+            log("amount", amount),
+            log("escrow", pet.escrow),
+        ],
         data: None,
     })
 }
 
-pub fn query<S: Storage, A: Api, Q: Querier>(
-    deps: &Extern<S, A, Q>,
-    msg: QueryMsg,
-) -> StdResult<Binary> {
-    match msg {
-        QueryMsg::Query {} => to_binary(&query_all(deps)?),
+pub fn try_withdraw_escrow<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    pet_id: u64,
+    amount: Option<Uint128>,
+) -> StdResult<HandleResponse> {
+    let config_state = config_read(&deps.storage).load()?;
+    let mut pet = load_pet(&deps.storage, pet_id)?;
+    if env.message.sender != pet.owner {
+        return Err(StdError::Unauthorized { backtrace: None });
+    }
+
+    // FOOD the pet already needed can't be taken back
+    let mut messages = apply_escrow(&mut deps.storage, &config_state, &mut pet, env.block.time)?;
+    let amount = amount.unwrap_or(pet.escrow);
+    if amount > pet.escrow {
+        return Err(StdError::GenericErr {
+            msg: format!("Escrow only has {} FOOD", pet.escrow),
+            backtrace: None,
+        });
+    }
+    pet.escrow = Uint128(pet.escrow.u128() - amount.u128());
+    save_pet(&mut deps.storage, &pet)?;
+
+    if amount.u128() > 0 {
+        let transfer_msg = FoodHandleMsg::Transfer {
+            recipient: pet.owner.clone(),
+            amount,
+            padding: None,
+        };
+        messages.push(transfer_msg.to_cosmos_msg(
+            config_state.food_token_code_hash,
+            config_state.food_token_addr,
+            None,
+        )?);
+    }
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![
+            log("action", "withdraw_escrow"),
+            log("pet_id", pet_id),
+            log("amount", amount),
+        ],
+        data: Some(to_binary(&HandleAnswer::WithdrawEscrow { amount })?),
+    })
+}
+
+/// Marks the pet as dead and writes its memorial, only the first time its death is recorded.
+fn record_death<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    config_state: &Config,
+    pet: &mut State,
+    now: u64,
+) -> StdResult<()> {
+    if pet.starved {
+        return Ok(());
     }
+    pet.record_death(now);
+
+    let died_at = pet.died_at.unwrap_or(now);
+    let cause = match pet.ill_since {
+        Some(since) => {
+            let illness = Illness {
+                since,
+                until: died_at,
+                outcome: IllnessOutcome::Died,
+            };
+            store_illness(&mut deps.storage, pet.id, &illness)?;
+            DeathCause::Illness
+        }
+        None => DeathCause::Starvation,
+    };
+    let memorial = Memorial {
+        pet_id: pet.id,
+        name: pet.name.clone(),
+        owner: pet.owner.clone(),
+        born_at: pet.born_at,
+        died_at,
+        lifespan: died_at.saturating_sub(pet.alive_since),
+        cause,
+        stage: pet.stage_at(died_at),
+        happiness: pet
+            .happiness
+            .value_at(died_at, stat_decay(config_state, pet, StatKind::Happiness)),
+        cleanliness: pet.cleanliness.value_at(
+            died_at,
+            stat_decay(config_state, pet, StatKind::Cleanliness),
+        ),
+        energy: pet
+            .energy
+            .value_at(died_at, stat_decay(config_state, pet, StatKind::Energy)),
+        food_eaten: pet.food_eaten,
+        feed_count: pet.feed_count,
+        generation: pet.generation,
+    };
+    let owner = deps.api.canonical_address(&pet.owner)?;
+    store_memorial(&mut deps.storage, &owner, &memorial)?;
+
+    update_leaderboards(&mut deps.storage, pet, died_at)
 }
 
-fn query_all<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>) -> StdResult<QueryResponse> {
-    let state = config_read(&deps.storage).load()?;
-    let now = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_millis();
+pub fn try_settle<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    pet_id: u64,
+) -> StdResult<HandleResponse> {
+    let now = env.block.time;
+    let mut config_state = config_read(&deps.storage).load()?;
+    let mut pet = load_pet(&deps.storage, pet_id)?;
+
+    // Keepers also settle escrow debits of pets whose countdown ran out
+    let escrow_before = pet.escrow.u128();
+    let mut messages = apply_escrow(&mut deps.storage, &config_state, &mut pet, now)?;
+    if pet.starved || (pet.is_alive(now) && messages.is_empty()) {
+        return Err(StdError::GenericErr {
+            msg: format!("Pet {} has nothing to settle", pet_id),
+            backtrace: None,
+        });
+    }
+
+    let mut logs = vec![log("action", "settle"), log("pet_id", pet_id)];
+    let mut bounty = 0;
+    if !pet.is_alive(now) {
+        record_death(deps, &config_state, &mut pet, now)?;
+        logs.push(log("died_at", pet.died_at.unwrap_or_default()));
 
-    Ok(QueryResponse {
-        is_alive: !state.starved && state.full_until > now,
+        // Bounty is only paid for deaths, as long as the pool can afford it
+        bounty = config_state
+            .settle_bounty
+            .u128()
+            .min(config_state.pool.u128());
+    }
+    save_pet(&mut deps.storage, &pet)?;
+
+    if bounty > 0 {
+        config_state.pool = Uint128(config_state.pool.u128() - bounty);
+        config(&mut deps.storage).save(&config_state)?;
+
+        let transfer_msg = FoodHandleMsg::Transfer {
+            recipient: env.message.sender.clone(),
+            amount: Uint128(bounty),
+            padding: None,
+        };
+        messages.push(transfer_msg.to_cosmos_msg(
+            config_state.food_token_code_hash,
+            config_state.food_token_addr,
+            None,
+        )?);
+    }
+
+    logs.push(log("keeper", env.message.sender));
+    logs.push(log("bounty", bounty));
+    let escrow_used = escrow_before - pet.escrow.u128();
+    if escrow_used > 0 {
+        logs.push(log("escrow_used", escrow_used));
+    }
+
+    Ok(HandleResponse {
+        messages,
+        log: logs,
+        data: Some(to_binary(&HandleAnswer::Settle {
+            bounty: Uint128(bounty),
+        })?),
     })
 }
+
+pub fn try_hibernate<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    food: Food,
+    from: HumanAddr,
+    amount: Uint128,
+    pet_id: u64,
+    until: u64,
+) -> StdResult<HandleResponse> {
+    let now = env.block.time;
+
+    let config_state = config_read(&deps.storage).load()?;
+    if food.contract != config_state.food_token_addr {
+        return Err(StdError::GenericErr {
+            msg: "Hibernation is paid with FOOD".to_string(),
+            backtrace: None,
+        });
+    }
+    if amount != config_state.hibernation_fee {
+        return Err(StdError::GenericErr {
+            msg: format!("Hibernation costs {} FOOD", config_state.hibernation_fee),
+            backtrace: None,
+        });
+    }
+
+    let mut pet = load_pet(&deps.storage, pet_id)?;
+    if from != pet.owner {
+        return Err(StdError::Unauthorized { backtrace: None });
+    }
+    let mut messages = apply_escrow(&mut deps.storage, &config_state, &mut pet, now)?;
+    if !pet.is_alive(now) {
+        return Err(StdError::GenericErr {
+            msg: format!("Pet {} is dead", pet_id),
+            backtrace: None,
+        });
+    }
+    if pet.is_hibernating(now) {
+        return Err(StdError::GenericErr {
+            msg: format!("Pet {} is hibernating", pet_id),
+            backtrace: None,
+        });
+    }
+    if pet.ill_since.is_some() {
+        return Err(StdError::GenericErr {
+            msg: format!("Pet {} is ill", pet_id),
+            backtrace: None,
+        });
+    }
+    // Otherwise hibernating back to back would keep a pet alive cheaper than feeding it
+    if pet.hibernated_since_fed {
+        return Err(StdError::GenericErr {
+            msg: format!("Pet {} has to be fed before hibernating again", pet_id),
+            backtrace: None,
+        });
+    }
+    if until <= now || until - now > config_state.max_hibernation {
+        return Err(StdError::GenericErr {
+            msg: format!(
+                "Hibernation must end in the future, and last at most {} seconds",
+                config_state.max_hibernation
+            ),
+            backtrace: None,
+        });
+    }
+
+    // Time frozen by a previous, finished hibernation is kept
+    pet.wake(now);
+    pet.hibernation = Some(Hibernation { since: now, until });
+    pet.hibernated_since_fed = true;
+    save_pet(&mut deps.storage, &pet)?;
+
+    let burn_msg = FoodHandleMsg::Burn {
+        amount,
+        padding: None,
+    };
+    messages.push(burn_msg.to_cosmos_msg(food.code_hash, food.contract, None)?);
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![
+            log("action", "hibernate"),
+            log("pet_id", pet_id),
+            log("until", until),
+        ],
+        data: None,
+    })
+}
+
+pub fn try_approve_breeding<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    pet_id: u64,
+    partner_id: Option<u64>,
+) -> StdResult<HandleResponse> {
+    let mut pet = load_pet(&deps.storage, pet_id)?;
+    if env.message.sender != pet.owner {
+        return Err(StdError::Unauthorized { backtrace: None });
+    }
+
+    pet.breeding_partner = partner_id;
+    save_pet(&mut deps.storage, &pet)?;
+
+    // Empty partner id means the approval was withdrawn
+    let partner_id = partner_id.map(|id| id.to_string()).unwrap_or_default();
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "approve_breeding"),
+            log("pet_id", pet_id),
+            log("partner_id", partner_id),
+        ],
+        data: Some(to_binary(&HandleAnswer::ApproveBreeding {
+            status: Success,
+        })?),
+    })
+}
+
+pub fn try_breed<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    food: Food,
+    from: HumanAddr,
+    amount: Uint128,
+    (pet_id, partner_id): (u64, u64),
+    name: String,
+) -> StdResult<HandleResponse> {
+    let now = env.block.time;
+
+    let mut config_state = config_read(&deps.storage).load()?;
+    if food.contract != config_state.food_token_addr {
+        return Err(StdError::GenericErr {
+            msg: "Breeding is paid with FOOD".to_string(),
+            backtrace: None,
+        });
+    }
+    if amount != config_state.breeding_fee {
+        return Err(StdError::GenericErr {
+            msg: format!("Breeding costs {} FOOD", config_state.breeding_fee),
+            backtrace: None,
+        });
+    }
+    if pet_id == partner_id {
+        return Err(StdError::GenericErr {
+            msg: "Pet can't be bred with itself".to_string(),
+            backtrace: None,
+        });
+    }
+    check_name(&deps.storage, &name)?;
+
+    let mut pet = load_pet(&deps.storage, pet_id)?;
+    let mut partner = load_pet(&deps.storage, partner_id)?;
+    if from != pet.owner {
+        return Err(StdError::Unauthorized { backtrace: None });
+    }
+    // Consent is given for a single breeding
+    if from != partner.owner {
+        if partner.breeding_partner != Some(pet_id) {
+            return Err(StdError::Unauthorized { backtrace: None });
+        }
+        partner.breeding_partner = None;
+    }
+    let mut messages = apply_escrow(&mut deps.storage, &config_state, &mut pet, now)?;
+    messages.extend(apply_escrow(
+        &mut deps.storage,
+        &config_state,
+        &mut partner,
+        now,
+    )?);
+    save_pet(&mut deps.storage, &pet)?;
+    save_pet(&mut deps.storage, &partner)?;
+
+    for parent in &[&pet, &partner] {
+        if !parent.is_alive(now) || parent.is_hibernating(now) {
+            return Err(StdError::GenericErr {
+                msg: format!("Pet {} can't breed right now", parent.id),
+                backtrace: None,
+            });
+        }
+        if parent.stage_at(now) < LifeStage::Adult {
+            return Err(StdError::GenericErr {
+                msg: format!("Pet {} is too young to breed", parent.id),
+                backtrace: None,
+            });
+        }
+    }
+
+    config_state.pet_count += 1;
+    let child_id = config_state.pet_count;
+
+    // Each gene comes from one of the parents, and sometimes mutates
+    let mut prng = pet_prng(&mut config_state, &env, &from, child_id);
+    config(&mut deps.storage).save(&config_state)?;
+    let picks = prng.rand_bytes();
+    let mutations = prng.rand_bytes();
+    let random_genes = prng.rand_bytes();
+    let dna: Vec<u8> = (0..picks.len())
+        .map(|i| {
+            let parent = if picks[i] & 1 == 0 { &pet } else { &partner };
+            match parent.dna.as_slice().get(i) {
+                Some(gene) if mutations[i] >= MUTATION_CHANCE => *gene,
+                _ => random_genes[i],
+            }
+        })
+        .collect();
+
+    let mut child = State::new(
+        child_id,
+        name,
+        from.clone(),
+        now,
+        config_state.starvation_window,
+        Binary(dna),
+    );
+    child.parents = vec![pet_id, partner_id];
+    child.generation = pet.generation.max(partner.generation) + 1;
+    save_pet(&mut deps.storage, &child)?;
+
+    let owner = deps.api.canonical_address(&from)?;
+    add_owner_pet(&mut deps.storage, &owner, child_id)?;
+    add_child(&mut deps.storage, pet_id, child_id)?;
+    add_child(&mut deps.storage, partner_id, child_id)?;
+
+    let burn_msg = FoodHandleMsg::Burn {
+        amount,
+        padding: None,
+    };
+    messages.push(burn_msg.to_cosmos_msg(food.code_hash, food.contract, None)?);
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![
+            log("action", "breed"),
+            log("pet_id", child_id),
+            log("parents", format!("{},{}", pet_id, partner_id)),
+        ],
+        data: Some(to_binary(&HandleAnswer::Breed { pet_id: child_id })?),
+    })
+}
+
+pub fn try_wake<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    pet_id: u64,
+) -> StdResult<HandleResponse> {
+    let now = env.block.time;
+
+    let mut pet = load_pet(&deps.storage, pet_id)?;
+    if env.message.sender != pet.owner {
+        return Err(StdError::Unauthorized { backtrace: None });
+    }
+    if !pet.is_hibernating(now) {
+        return Err(StdError::GenericErr {
+            msg: format!("Pet {} is not hibernating", pet_id),
+            backtrace: None,
+        });
+    }
+
+    pet.wake(now);
+    save_pet(&mut deps.storage, &pet)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "wake"),
+            log("pet_id", pet_id),
+            log("full_until", pet.full_until),
+        ],
+        data: Some(to_binary(&HandleAnswer::Wake { status: Success })?),
+    })
+}
+
+/// FOOD needed to fill a starvation window, times the revival multiplier.
+fn revival_cost(config_state: &Config) -> Uint128 {
+    let window = config_state.starvation_window as u128;
+    let time_per_food = config_state.time_per_food as u128;
+    // Rounded up, a partially filled window still needs a whole meal
+    let meals = window / time_per_food + (window % time_per_food).min(1);
+
+    Uint128(meals.saturating_mul(config_state.revival_multiplier as u128))
+}
+
+/// Well kept pets digest better: feeding efficiency goes from 50%
+/// when all wellbeing stats are empty, to 100% when they are full.
+fn feeding_efficiency(config_state: &Config, pet: &State, now: u64) -> u64 {
+    let now = pet.stats_time(now);
+    let total = pet
+        .happiness
+        .value_at(now, stat_decay(config_state, pet, StatKind::Happiness))
+        + pet
+            .cleanliness
+            .value_at(now, stat_decay(config_state, pet, StatKind::Cleanliness))
+        + pet
+            .energy
+            .value_at(now, stat_decay(config_state, pet, StatKind::Energy));
+
+    50 + total * 50 / (3 * MAX_STAT)
+}
+
+fn wellbeing(config_state: &Config, pet: &State, now: u64) -> Wellbeing {
+    // Only a starved pet has zero satiation left
+    let seconds_left = pet.seconds_left(now);
+    let max_satiation = pet.traits.satiation_time(config_state.max_satiation);
+    let hunger = match seconds_left * MAX_STAT / max_satiation {
+        0 if seconds_left > 0 => 1,
+        hunger => hunger,
+    };
+
+    let stats_time = pet.stats_time(now);
+    Wellbeing {
+        hunger: hunger.min(MAX_STAT),
+        happiness: pet.happiness.value_at(
+            stats_time,
+            stat_decay(config_state, pet, StatKind::Happiness),
+        ),
+        cleanliness: pet.cleanliness.value_at(
+            stats_time,
+            stat_decay(config_state, pet, StatKind::Cleanliness),
+        ),
+        energy: pet
+            .energy
+            .value_at(stats_time, stat_decay(config_state, pet, StatKind::Energy)),
+        feeding_efficiency: feeding_efficiency(config_state, pet, now),
+    }
+}
+
+/// Loads a pet `player` is allowed to take care of, which must be alive and awake.
+/// Also returns the messages burning FOOD its escrow was debited.
+fn load_pet_for_care<S: Storage>(
+    storage: &mut S,
+    player: &HumanAddr,
+    now: u64,
+    pet_id: u64,
+) -> StdResult<(State, Vec<CosmosMsg>)> {
+    let config_state = config_read(storage).load()?;
+    let mut pet = load_pet(storage, pet_id)?;
+    if !can_feed(storage, &pet, player, now)? {
+        return Err(StdError::Unauthorized { backtrace: None });
+    }
+    let messages = apply_escrow(storage, &config_state, &mut pet, now)?;
+    if !pet.is_alive(now) {
+        return Err(StdError::GenericErr {
+            msg: format!("Pet {} is dead", pet_id),
+            backtrace: None,
+        });
+    }
+    if pet.is_hibernating(now) {
+        return Err(StdError::GenericErr {
+            msg: format!("Pet {} is hibernating", pet_id),
+            backtrace: None,
+        });
+    }
+    // Stats are set at `now` from here on, so a finished hibernation is folded in first
+    pet.wake(now);
+
+    Ok((pet, messages))
+}
+
+struct GameOutcome {
+    game: &'static str,
+    outcome: String,
+    won: bool,
+    payout_multiplier: u128,
+}
+
+fn play_game(game: &Game, roll: u8) -> StdResult<GameOutcome> {
+    match game {
+        Game::CoinFlip { guess } => {
+            let coin = if roll & 1 == 0 {
+                Coin::Heads
+            } else {
+                Coin::Tails
+            };
+            let outcome = match coin {
+                Coin::Heads => "heads",
+                Coin::Tails => "tails",
+            };
+            Ok(GameOutcome {
+                game: "coin_flip",
+                outcome: outcome.to_string(),
+                won: &coin == guess,
+                payout_multiplier: COIN_FLIP_PAYOUT,
+            })
+        }
+        Game::HideAndSeek { spot } => {
+            if *spot >= HIDING_SPOTS {
+                return Err(StdError::GenericErr {
+                    msg: format!("Pet can only hide in spots 0 to {}", HIDING_SPOTS - 1),
+                    backtrace: None,
+                });
+            }
+            let hidden_in = roll % HIDING_SPOTS;
+            Ok(GameOutcome {
+                game: "hide_and_seek",
+                outcome: hidden_in.to_string(),
+                won: hidden_in == *spot,
+                payout_multiplier: HIDE_AND_SEEK_PAYOUT,
+            })
+        }
+    }
+}
+
+/// Plays with the pet, optionally a game with a FOOD `wager` which is already in the Pet's
+/// balance. Lost wagers go to the pool, wins are paid from it.
+pub fn try_play<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    player: HumanAddr,
+    pet_id: u64,
+    game: Option<Game>,
+    wager: Uint128,
+) -> StdResult<HandleResponse> {
+    let now = env.block.time;
+    let mut config_state = config_read(&deps.storage).load()?;
+    let (mut pet, mut messages) = load_pet_for_care(&mut deps.storage, &player, now, pet_id)?;
+
+    let energy = pet
+        .energy
+        .value_at(now, stat_decay(&config_state, &pet, StatKind::Energy));
+    if energy < PLAY_ENERGY {
+        return Err(StdError::GenericErr {
+            msg: "Pet is too tired to play".to_string(),
+            backtrace: None,
+        });
+    }
+    let neglected = is_neglected(&config_state, &pet, now);
+    let happiness = pet
+        .happiness
+        .value_at(now, stat_decay(&config_state, &pet, StatKind::Happiness));
+    pet.energy.set(now, energy - PLAY_ENERGY);
+    pet.happiness.set(now, happiness + PLAY_HAPPINESS);
+
+    let rolls = pet_prng(&mut config_state, &env, &player, pet_id).rand_bytes();
+    let mut logs = vec![log("action", "play"), log("pet_id", pet_id)];
+    let mut answer = HandleAnswer::Play { status: Success };
+    if let Some(game) = game {
+        let result = play_game(&game, rolls[0])?;
+        let max_payout = wager.u128().saturating_mul(result.payout_multiplier);
+        let payout = if result.won { max_payout } else { 0 };
+
+        if wager.u128() > 0 {
+            let pool = config_state.pool.u128().saturating_add(wager.u128());
+            if pool < max_payout {
+                return Err(StdError::GenericErr {
+                    msg: format!("Pool can't cover a win of {} FOOD", max_payout),
+                    backtrace: None,
+                });
+            }
+            config_state.pool = Uint128(pool - payout);
+        }
+        if payout > 0 {
+            let transfer_msg = FoodHandleMsg::Transfer {
+                recipient: player,
+                amount: Uint128(payout),
+                padding: None,
+            };
+            messages.push(transfer_msg.to_cosmos_msg(
+                config_state.food_token_code_hash.clone(),
+                config_state.food_token_addr.clone(),
+                None,
+            )?);
+        }
+
+        logs.extend(vec![
+            log("game", result.game),
+            log("outcome", result.outcome),
+            log("won", result.won),
+            log("wager", wager),
+            log("payout", payout),
+        ]);
+        answer = HandleAnswer::Game {
+            won: result.won,
+            payout: Uint128(payout),
+        };
+    }
+    if roll_illness(&mut pet, now, neglected, rolls[1]) {
+        logs.push(log("illness", "fell_ill"));
+    }
+    config(&mut deps.storage).save(&config_state)?;
+    save_pet(&mut deps.storage, &pet)?;
+
+    Ok(HandleResponse {
+        messages,
+        log: logs,
+        data: Some(to_binary(&answer)?),
+    })
+}
+
+pub fn try_clean<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    pet_id: u64,
+) -> StdResult<HandleResponse> {
+    let (mut pet, messages) = load_pet_for_care(
+        &mut deps.storage,
+        &env.message.sender,
+        env.block.time,
+        pet_id,
+    )?;
+    pet.cleanliness.set(env.block.time, MAX_STAT);
+    save_pet(&mut deps.storage, &pet)?;
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![log("action", "clean"), log("pet_id", pet_id)],
+        data: Some(to_binary(&HandleAnswer::Clean { status: Success })?),
+    })
+}
+
+pub fn try_sleep<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    pet_id: u64,
+) -> StdResult<HandleResponse> {
+    let (mut pet, messages) = load_pet_for_care(
+        &mut deps.storage,
+        &env.message.sender,
+        env.block.time,
+        pet_id,
+    )?;
+    pet.energy.set(env.block.time, MAX_STAT);
+    save_pet(&mut deps.storage, &pet)?;
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![log("action", "sleep"), log("pet_id", pet_id)],
+        data: Some(to_binary(&HandleAnswer::Sleep { status: Success })?),
+    })
+}
+
+pub fn try_transfer_ownership<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    pet_id: u64,
+    new_owner: HumanAddr,
+) -> StdResult<HandleResponse> {
+    let mut pet = load_pet(&deps.storage, pet_id)?;
+    if env.message.sender != pet.owner {
+        return Err(StdError::Unauthorized { backtrace: None });
+    }
+
+    // Offering the pet to someone else replaces the previous offer
+    pet.pending_owner = Some(new_owner.clone());
+    save_pet(&mut deps.storage, &pet)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "transfer_ownership"),
+            log("pet_id", pet_id),
+            log("from", pet.owner),
+            log("to", new_owner),
+        ],
+        data: Some(to_binary(&HandleAnswer::TransferOwnership {
+            status: Success,
+        })?),
+    })
+}
+
+pub fn try_accept_ownership<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    pet_id: u64,
+) -> StdResult<HandleResponse> {
+    let mut pet = load_pet(&deps.storage, pet_id)?;
+    if pet.pending_owner.as_ref() != Some(&env.message.sender) {
+        return Err(StdError::Unauthorized { backtrace: None });
+    }
+
+    let (from, messages) =
+        change_owner(deps, &mut pet, env.message.sender.clone(), env.block.time)?;
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![
+            log("action", "accept_ownership"),
+            log("pet_id", pet_id),
+            log("from", from),
+            log("to", env.message.sender),
+        ],
+        data: Some(to_binary(&HandleAnswer::AcceptOwnership {
+            status: Success,
+        })?),
+    })
+}
+
+pub fn try_cancel_ownership_transfer<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    pet_id: u64,
+) -> StdResult<HandleResponse> {
+    let mut pet = load_pet(&deps.storage, pet_id)?;
+    if env.message.sender != pet.owner {
+        return Err(StdError::Unauthorized { backtrace: None });
+    }
+
+    let pending_owner = match pet.pending_owner.take() {
+        Some(pending_owner) => pending_owner,
+        None => {
+            return Err(StdError::GenericErr {
+                msg: format!("Pet {} has no pending transfer", pet_id),
+                backtrace: None,
+            })
+        }
+    };
+    save_pet(&mut deps.storage, &pet)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "cancel_ownership_transfer"),
+            log("pet_id", pet_id),
+            log("to", pending_owner),
+        ],
+        data: Some(to_binary(&HandleAnswer::CancelOwnershipTransfer {
+            status: Success,
+        })?),
+    })
+}
+
+fn parse_token_id(token_id: &str) -> StdResult<u64> {
+    token_id.parse().map_err(|_| StdError::GenericErr {
+        msg: format!("Invalid token id: {}", token_id),
+        backtrace: None,
+    })
+}
+
+pub fn try_transfer_nft<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    recipient: HumanAddr,
+    token_id: String,
+) -> StdResult<HandleResponse> {
+    let mut pet = load_pet(&deps.storage, parse_token_id(&token_id)?)?;
+    if env.message.sender != pet.owner {
+        return Err(StdError::Unauthorized { backtrace: None });
+    }
+
+    let (_, messages) = change_owner(deps, &mut pet, recipient.clone(), env.block.time)?;
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![
+            log("action", "transfer_nft"),
+            log("pet_id", pet.id),
+            log("from", env.message.sender),
+            log("to", recipient),
+        ],
+        data: Some(to_binary(&HandleAnswer::TransferNft { status: Success })?),
+    })
+}
+
+pub fn try_send_nft<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    contract: HumanAddr,
+    token_id: String,
+    msg: Option<Binary>,
+) -> StdResult<HandleResponse> {
+    let mut pet = load_pet(&deps.storage, parse_token_id(&token_id)?)?;
+    if env.message.sender != pet.owner {
+        return Err(StdError::Unauthorized { backtrace: None });
+    }
+
+    let (_, mut messages) = change_owner(deps, &mut pet, contract.clone(), env.block.time)?;
+
+    // Contracts which didn't register just receive the pet, like with TransferNft
+    if let Some(code_hash) = get_receiver_hash(&deps.storage, &contract) {
+        let receive_msg = Snip721ReceiveMsg::ReceiveNft {
+            sender: env.message.sender.clone(),
+            token_id,
+            msg,
+        };
+        messages.push(receive_msg.to_cosmos_msg(code_hash?, contract.clone(), None)?);
+    }
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![
+            log("action", "send_nft"),
+            log("pet_id", pet.id),
+            log("from", env.message.sender),
+            log("to", contract),
+        ],
+        data: Some(to_binary(&HandleAnswer::SendNft { status: Success })?),
+    })
+}
+
+pub fn try_register_receive_nft<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    code_hash: String,
+) -> StdResult<HandleResponse> {
+    set_receiver_hash(&mut deps.storage, &env.message.sender, code_hash);
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![log("register_status", "success")],
+        data: Some(to_binary(&HandleAnswer::RegisterReceiveNft {
+            status: Success,
+        })?),
+    })
+}
+
+/// Hands the pet over to `new_owner`, returning the previous owner and the messages
+/// settling its escrow.
+fn change_owner<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    pet: &mut State,
+    new_owner: HumanAddr,
+    now: u64,
+) -> StdResult<(HumanAddr, Vec<CosmosMsg>)> {
+    // Escrow the pet hasn't eaten yet goes back to the owner who deposited it
+    let config_state = config_read(&deps.storage).load()?;
+    let mut messages = apply_escrow(&mut deps.storage, &config_state, pet, now)?;
+    if pet.escrow.u128() > 0 {
+        let transfer_msg = FoodHandleMsg::Transfer {
+            recipient: pet.owner.clone(),
+            amount: pet.escrow,
+            padding: None,
+        };
+        messages.push(transfer_msg.to_cosmos_msg(
+            config_state.food_token_code_hash,
+            config_state.food_token_addr,
+            None,
+        )?);
+        pet.escrow = Uint128::zero();
+    }
+
+    let previous_owner = deps.api.canonical_address(&pet.owner)?;
+    let canonical_new_owner = deps.api.canonical_address(&new_owner)?;
+    remove_owner_pet(&mut deps.storage, &previous_owner, pet.id)?;
+    add_owner_pet(&mut deps.storage, &canonical_new_owner, pet.id)?;
+
+    // Caretakers, breeding consent and listing were chosen by the previous owner, not the new one
+    write_caretakers(&mut deps.storage, pet.id, &[])?;
+    if pet.listed {
+        remove_from_leaderboards(&mut deps.storage, pet.id)?;
+        pet.listed = false;
+    }
+    pet.breeding_partner = None;
+    pet.pending_owner = None;
+
+    let from = std::mem::replace(&mut pet.owner, new_owner);
+    save_pet(&mut deps.storage, pet)?;
+
+    Ok((from, messages))
+}
+
+/// Owner can always feed the pet, caretakers only until their permission expires.
+fn can_feed<S: Storage>(storage: &S, pet: &State, from: &HumanAddr, now: u64) -> StdResult<bool> {
+    if &pet.owner == from {
+        return Ok(true);
+    }
+
+    let caretakers = read_caretakers(storage, pet.id)?;
+    Ok(caretakers
+        .iter()
+        .any(|caretaker| &caretaker.address == from && !caretaker.is_expired(now)))
+}
+
+/// Decay of a `kind` stat per hour, after the pet's equipped items are applied.
+fn stat_decay(config_state: &Config, pet: &State, kind: StatKind) -> u64 {
+    let decay = match kind {
+        StatKind::Happiness => config_state.happiness_decay,
+        StatKind::Cleanliness => config_state.cleanliness_decay,
+        StatKind::Energy => config_state.energy_decay,
+    };
+    decay.saturating_mul(pet.decay_percent(kind)) / 100
+}
+
+/// Stores the `kind` stat's current value, so equipping or removing items doesn't change the
+/// decay of the time before now. Changing the configured rates does, see `set_decay_config`.
+fn settle_stat(config_state: &Config, pet: &mut State, kind: StatKind, now: u64) {
+    let stats_time = pet.stats_time(now);
+    let decay = stat_decay(config_state, pet, kind);
+    let stat = pet.stat_mut(kind);
+    let value = stat.value_at(stats_time, decay);
+    stat.set(stats_time, value);
+}
+
+pub fn try_add_item<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    from: HumanAddr,
+    accessory: Accessory,
+    pet_id: u64,
+    token_id: Option<String>,
+    amount: Uint128,
+) -> StdResult<HandleResponse> {
+    // Strangers could otherwise fill up the inventory so the owner can't add anything
+    let mut pet = load_pet(&deps.storage, pet_id)?;
+    if !can_feed(&deps.storage, &pet, &from, env.block.time)? {
+        return Err(StdError::Unauthorized { backtrace: None });
+    }
+
+    // Units of a SNIP-20 accessory go to the same item
+    let existing = pet
+        .inventory
+        .iter_mut()
+        .find(|item| item.accessory.contract == accessory.contract && token_id.is_none());
+    match existing {
+        Some(item) => item.amount = Uint128(item.amount.u128().saturating_add(amount.u128())),
+        None => {
+            if pet.inventory.len() >= MAX_INVENTORY {
+                return Err(StdError::GenericErr {
+                    msg: format!(
+                        "Pet {} can't carry more than {} items",
+                        pet_id, MAX_INVENTORY
+                    ),
+                    backtrace: None,
+                });
+            }
+            pet.inventory.push(Item {
+                accessory: accessory.clone(),
+                token_id: token_id.clone(),
+                amount,
+                equipped: false,
+            });
+        }
+    }
+    save_pet(&mut deps.storage, &pet)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "add_item"),
+            log("pet_id", pet_id),
+            log("contract", accessory.contract),
+            log("token_id", token_id.unwrap_or_default()),
+            log("amount", amount),
+        ],
+        data: None,
+    })
+}
+
+fn find_item(pet: &State, contract: &HumanAddr, token_id: &Option<String>) -> StdResult<usize> {
+    pet.inventory
+        .iter()
+        .position(|item| &item.accessory.contract == contract && &item.token_id == token_id)
+        .ok_or_else(|| StdError::GenericErr {
+            msg: format!("Pet {} doesn't have this item", pet.id),
+            backtrace: None,
+        })
+}
+
+pub fn try_equip<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    pet_id: u64,
+    contract: HumanAddr,
+    token_id: Option<String>,
+) -> StdResult<HandleResponse> {
+    let now = env.block.time;
+    let config_state = config_read(&deps.storage).load()?;
+    let mut pet = load_pet(&deps.storage, pet_id)?;
+    if env.message.sender != pet.owner {
+        return Err(StdError::Unauthorized { backtrace: None });
+    }
+    let messages = apply_escrow(&mut deps.storage, &config_state, &mut pet, now)?;
+    if !pet.is_alive(now) {
+        return Err(StdError::GenericErr {
+            msg: format!("Pet {} is dead", pet_id),
+            backtrace: None,
+        });
+    }
+
+    let index = find_item(&pet, &contract, &token_id)?;
+    let stat = pet.inventory[index].accessory.stat;
+    if pet
+        .inventory
+        .iter()
+        .any(|item| item.equipped && item.accessory.stat == stat)
+    {
+        return Err(StdError::GenericErr {
+            msg: format!("Pet {} already wears an item for {:?}", pet_id, stat),
+            backtrace: None,
+        });
+    }
+
+    settle_stat(&config_state, &mut pet, stat, now);
+    pet.inventory[index].equipped = true;
+    save_pet(&mut deps.storage, &pet)?;
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![
+            log("action", "equip"),
+            log("pet_id", pet_id),
+            log("contract", contract),
+        ],
+        data: Some(to_binary(&HandleAnswer::Equip { status: Success })?),
+    })
+}
+
+pub fn try_unequip<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    pet_id: u64,
+    contract: HumanAddr,
+    token_id: Option<String>,
+) -> StdResult<HandleResponse> {
+    let now = env.block.time;
+    let config_state = config_read(&deps.storage).load()?;
+    let mut pet = load_pet(&deps.storage, pet_id)?;
+    if env.message.sender != pet.owner {
+        return Err(StdError::Unauthorized { backtrace: None });
+    }
+    let mut messages = apply_escrow(&mut deps.storage, &config_state, &mut pet, now)?;
+
+    let index = find_item(&pet, &contract, &token_id)?;
+    let stat = pet.inventory[index].accessory.stat;
+    settle_stat(&config_state, &mut pet, stat, now);
+    let item = pet.inventory.remove(index);
+    save_pet(&mut deps.storage, &pet)?;
+
+    let accessory = item.accessory;
+    let cosmos_msg = match item.token_id {
+        Some(token_id) => Snip721HandleMsg::TransferNft {
+            recipient: pet.owner,
+            token_id,
+            memo: None,
+            padding: None,
+        }
+        .to_cosmos_msg(accessory.code_hash, accessory.contract, None)?,
+        None => FoodHandleMsg::Transfer {
+            recipient: pet.owner,
+            amount: item.amount,
+            padding: None,
+        }
+        .to_cosmos_msg(accessory.code_hash, accessory.contract, None)?,
+    };
+    messages.push(cosmos_msg);
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![
+            log("action", "unequip"),
+            log("pet_id", pet_id),
+            log("contract", contract),
+        ],
+        data: Some(to_binary(&HandleAnswer::Unequip { status: Success })?),
+    })
+}
+
+pub fn try_set_metadata<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    pet_id: u64,
+    name: Option<String>,
+    bio: Option<String>,
+    avatar_uri: Option<String>,
+) -> StdResult<HandleResponse> {
+    let mut pet = load_pet(&deps.storage, pet_id)?;
+    if env.message.sender != pet.owner {
+        return Err(StdError::Unauthorized { backtrace: None });
+    }
+
+    if let Some(name) = name {
+        check_name(&deps.storage, &name)?;
+        pet.name = name;
+    }
+    if let Some(bio) = bio {
+        if bio.len() > MAX_BIO_LEN {
+            return Err(StdError::GenericErr {
+                msg: format!("Bio can't be longer than {} bytes", MAX_BIO_LEN),
+                backtrace: None,
+            });
+        }
+        check_banned_words(&deps.storage, &bio)?;
+        pet.bio = Some(bio).filter(|bio| !bio.is_empty());
+    }
+    if let Some(avatar_uri) = avatar_uri {
+        if avatar_uri.len() > MAX_AVATAR_URI_LEN {
+            return Err(StdError::GenericErr {
+                msg: format!(
+                    "Avatar URI can't be longer than {} bytes",
+                    MAX_AVATAR_URI_LEN
+                ),
+                backtrace: None,
+            });
+        }
+        pet.avatar_uri = Some(avatar_uri).filter(|uri| !uri.is_empty());
+    }
+    save_pet(&mut deps.storage, &pet)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![log("action", "set_metadata"), log("pet_id", pet_id)],
+        data: Some(to_binary(&HandleAnswer::SetMetadata { status: Success })?),
+    })
+}
+
+fn check_name<S: ReadonlyStorage>(storage: &S, name: &str) -> StdResult<()> {
+    if !is_valid_name(name) {
+        return Err(StdError::GenericErr {
+            msg: "Name is not in the expected format (3-30 UTF-8 bytes)".to_string(),
+            backtrace: None,
+        });
+    }
+    check_banned_words(storage, name)
+}
+
+fn is_valid_name(name: &str) -> bool {
+    (3..=30).contains(&name.len())
+}
+
+fn check_banned_words<S: ReadonlyStorage>(storage: &S, text: &str) -> StdResult<()> {
+    let text = text.to_lowercase();
+    if read_banned_words(storage)?
+        .iter()
+        .any(|word| text.contains(word.as_str()))
+    {
+        return Err(StdError::GenericErr {
+            msg: "Text contains a banned word".to_string(),
+            backtrace: None,
+        });
+    }
+
+    Ok(())
+}
+
+pub fn try_set_listing<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    pet_id: u64,
+    public: bool,
+) -> StdResult<HandleResponse> {
+    let now = env.block.time;
+    let config_state = config_read(&deps.storage).load()?;
+    let mut pet = load_pet(&deps.storage, pet_id)?;
+    if env.message.sender != pet.owner {
+        return Err(StdError::Unauthorized { backtrace: None });
+    }
+
+    let messages = apply_escrow(&mut deps.storage, &config_state, &mut pet, now)?;
+    pet.listed = public;
+    if public {
+        update_leaderboards(&mut deps.storage, &pet, now)?;
+    } else {
+        remove_from_leaderboards(&mut deps.storage, pet_id)?;
+    }
+    save_pet(&mut deps.storage, &pet)?;
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![
+            log("action", "set_listing"),
+            log("pet_id", pet_id),
+            log("public", public),
+        ],
+        data: Some(to_binary(&HandleAnswer::SetListing { status: Success })?),
+    })
+}
+
+pub fn try_add_caretaker<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    pet_id: u64,
+    address: HumanAddr,
+    expiration: Option<u64>,
+) -> StdResult<HandleResponse> {
+    let pet = load_pet(&deps.storage, pet_id)?;
+    if env.message.sender != pet.owner {
+        return Err(StdError::Unauthorized { backtrace: None });
+    }
+
+    // Adding an existing caretaker again only updates the expiration
+    let mut caretakers = read_caretakers(&deps.storage, pet_id)?;
+    caretakers.retain(|caretaker| caretaker.address != address);
+    caretakers.push(Caretaker {
+        address: address.clone(),
+        expiration,
+    });
+    write_caretakers(&mut deps.storage, pet_id, &caretakers)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "add_caretaker"),
+            log("pet_id", pet_id),
+            log("caretaker", address),
+        ],
+        data: Some(to_binary(&HandleAnswer::AddCaretaker { status: Success })?),
+    })
+}
+
+pub fn try_remove_caretaker<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    pet_id: u64,
+    address: HumanAddr,
+) -> StdResult<HandleResponse> {
+    let pet = load_pet(&deps.storage, pet_id)?;
+    if env.message.sender != pet.owner {
+        return Err(StdError::Unauthorized { backtrace: None });
+    }
+
+    let mut caretakers = read_caretakers(&deps.storage, pet_id)?;
+    caretakers.retain(|caretaker| caretaker.address != address);
+    write_caretakers(&mut deps.storage, pet_id, &caretakers)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![
+            log("action", "remove_caretaker"),
+            log("pet_id", pet_id),
+            log("caretaker", address),
+        ],
+        data: Some(to_binary(&HandleAnswer::RemoveCaretaker {
+            status: Success,
+        })?),
+    })
+}
+
+pub fn try_set_key<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    key: String,
+) -> StdResult<HandleResponse> {
+    let vk = ViewingKey(key);
+
+    let message_sender = deps.api.canonical_address(&env.message.sender)?;
+    write_viewing_key(&mut deps.storage, &message_sender, &vk);
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::SetViewingKey { status: Success })?),
+    })
+}
+
+pub fn try_create_key<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    entropy: String,
+) -> StdResult<HandleResponse> {
+    let config_state = config_read(&deps.storage).load()?;
+
+    let key = ViewingKey::new(&env, &config_state.prng_seed, entropy.as_bytes());
+
+    let message_sender = deps.api.canonical_address(&env.message.sender)?;
+    write_viewing_key(&mut deps.storage, &message_sender, &key);
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::CreateViewingKey { key })?),
+    })
+}
+
+fn set_feeding_config<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    starvation_window: Option<u64>,
+    time_per_food: Option<u64>,
+    max_satiation: Option<u64>,
+) -> StdResult<HandleResponse> {
+    let mut config_state = config_read(&deps.storage).load()?;
+    check_if_admin(&config_state, &env.message.sender)?;
+
+    if let Some(starvation_window) = starvation_window {
+        config_state.starvation_window = starvation_window;
+    }
+    if let Some(time_per_food) = time_per_food {
+        config_state.time_per_food = time_per_food;
+    }
+    if let Some(max_satiation) = max_satiation {
+        config_state.max_satiation = max_satiation;
+    }
+
+    check_feeding_config(&config_state)?;
+    config(&mut deps.storage).save(&config_state)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::SetFeedingConfig {
+            status: Success,
+        })?),
+    })
+}
+
+fn set_decay_config<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    happiness_decay: Option<u64>,
+    cleanliness_decay: Option<u64>,
+    energy_decay: Option<u64>,
+) -> StdResult<HandleResponse> {
+    let mut config_state = config_read(&deps.storage).load()?;
+    check_if_admin(&config_state, &env.message.sender)?;
+
+    // Stats aren't settled first, so new rates apply since each stat's last update
+    let decays = [happiness_decay, cleanliness_decay, energy_decay];
+    if decays.iter().any(|decay| decay.unwrap_or(0) > MAX_STAT) {
+        return Err(StdError::GenericErr {
+            msg: format!("Decay can't be more than {} points per hour", MAX_STAT),
+            backtrace: None,
+        });
+    }
+    if let Some(happiness_decay) = happiness_decay {
+        config_state.happiness_decay = happiness_decay;
+    }
+    if let Some(cleanliness_decay) = cleanliness_decay {
+        config_state.cleanliness_decay = cleanliness_decay;
+    }
+    if let Some(energy_decay) = energy_decay {
+        config_state.energy_decay = energy_decay;
+    }
+    config(&mut deps.storage).save(&config_state)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::SetDecayConfig {
+            status: Success,
+        })?),
+    })
+}
+
+fn set_revival_config<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    enabled: Option<bool>,
+    multiplier: Option<u64>,
+) -> StdResult<HandleResponse> {
+    let mut config_state = config_read(&deps.storage).load()?;
+    check_if_admin(&config_state, &env.message.sender)?;
+
+    if let Some(enabled) = enabled {
+        config_state.revival_enabled = enabled;
+    }
+    if let Some(multiplier) = multiplier {
+        config_state.revival_multiplier = multiplier;
+    }
+    config(&mut deps.storage).save(&config_state)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::SetRevivalConfig {
+            status: Success,
+        })?),
+    })
+}
+
+fn set_settle_bounty<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    bounty: Uint128,
+) -> StdResult<HandleResponse> {
+    let mut config_state = config_read(&deps.storage).load()?;
+    check_if_admin(&config_state, &env.message.sender)?;
+
+    config_state.settle_bounty = bounty;
+    config(&mut deps.storage).save(&config_state)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::SetSettleBounty {
+            status: Success,
+        })?),
+    })
+}
+
+fn set_hibernation_config<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    fee: Option<Uint128>,
+    max_duration: Option<u64>,
+) -> StdResult<HandleResponse> {
+    let mut config_state = config_read(&deps.storage).load()?;
+    check_if_admin(&config_state, &env.message.sender)?;
+
+    if let Some(fee) = fee {
+        config_state.hibernation_fee = fee;
+    }
+    if let Some(max_duration) = max_duration {
+        config_state.max_hibernation = max_duration;
+    }
+    config(&mut deps.storage).save(&config_state)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::SetHibernationConfig {
+            status: Success,
+        })?),
+    })
+}
+
+fn set_breeding_fee<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    fee: Uint128,
+) -> StdResult<HandleResponse> {
+    let mut config_state = config_read(&deps.storage).load()?;
+    check_if_admin(&config_state, &env.message.sender)?;
+
+    config_state.breeding_fee = fee;
+    config(&mut deps.storage).save(&config_state)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::SetBreedingFee {
+            status: Success,
+        })?),
+    })
+}
+
+fn set_food<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    contract: HumanAddr,
+    code_hash: String,
+    nutrition: u64,
+    burn: bool,
+) -> StdResult<HandleResponse> {
+    let config_state = config_read(&deps.storage).load()?;
+    check_if_admin(&config_state, &env.message.sender)?;
+
+    if contract == config_state.food_token_addr {
+        return Err(StdError::GenericErr {
+            msg: "Primary FOOD is configured with SetFeedingConfig".to_string(),
+            backtrace: None,
+        });
+    }
+    if nutrition == 0 {
+        return Err(StdError::GenericErr {
+            msg: "Nutrition must be positive".to_string(),
+            backtrace: None,
+        });
+    }
+
+    let mut foods = read_foods(&deps.storage)?;
+    foods.retain(|food| food.contract != contract);
+    foods.push(Food {
+        contract: contract.clone(),
+        code_hash: code_hash.clone(),
+        nutrition,
+        burn,
+    });
+    write_foods(&mut deps.storage, &foods)?;
+
+    // New food contract has to know where to deliver the Receive callback
+    let register_msg = FoodHandleMsg::RegisterReceive {
+        code_hash: env.contract_code_hash,
+        padding: None,
+    };
+    let cosmos_msg = register_msg.to_cosmos_msg(code_hash, contract, None)?;
+
+    Ok(HandleResponse {
+        messages: vec![cosmos_msg],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::SetFood { status: Success })?),
+    })
+}
+
+fn remove_food<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    contract: HumanAddr,
+) -> StdResult<HandleResponse> {
+    let config_state = config_read(&deps.storage).load()?;
+    check_if_admin(&config_state, &env.message.sender)?;
+
+    let mut foods = read_foods(&deps.storage)?;
+    foods.retain(|food| food.contract != contract);
+    write_foods(&mut deps.storage, &foods)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::RemoveFood { status: Success })?),
+    })
+}
+
+fn withdraw_held_food<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    contract: HumanAddr,
+    code_hash: String,
+    recipient: HumanAddr,
+    amount: Option<Uint128>,
+) -> StdResult<HandleResponse> {
+    let config_state = config_read(&deps.storage).load()?;
+    check_if_admin(&config_state, &env.message.sender)?;
+
+    let held = read_held_food(&deps.storage, &contract)?;
+    let amount = amount.unwrap_or(held);
+    if amount > held {
+        return Err(StdError::GenericErr {
+            msg: format!("Only {} of this food is held", held),
+            backtrace: None,
+        });
+    }
+    write_held_food(
+        &mut deps.storage,
+        &contract,
+        Uint128(held.u128() - amount.u128()),
+    )?;
+
+    let mut messages = vec![];
+    if amount.u128() > 0 {
+        let transfer_msg = FoodHandleMsg::Transfer {
+            recipient: recipient.clone(),
+            amount,
+            padding: None,
+        };
+        messages.push(transfer_msg.to_cosmos_msg(code_hash, contract.clone(), None)?);
+    }
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![
+            log("action", "withdraw_held_food"),
+            log("food", contract),
+            log("recipient", recipient),
+            log("amount", amount),
+        ],
+        data: Some(to_binary(&HandleAnswer::WithdrawHeldFood { amount })?),
+    })
+}
+
+fn set_medicine<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    contract: HumanAddr,
+    code_hash: String,
+) -> StdResult<HandleResponse> {
+    let mut config_state = config_read(&deps.storage).load()?;
+    check_if_admin(&config_state, &env.message.sender)?;
+
+    if find_food(&deps.storage, &contract)?.is_some() {
+        return Err(StdError::GenericErr {
+            msg: "Medicine can't be a food".to_string(),
+            backtrace: None,
+        });
+    }
+
+    config_state.medicine = Some(Medicine {
+        contract: contract.clone(),
+        code_hash: code_hash.clone(),
+    });
+    config(&mut deps.storage).save(&config_state)?;
+
+    // Same as foods, medicine is used through the Receive callback
+    let register_msg = FoodHandleMsg::RegisterReceive {
+        code_hash: env.contract_code_hash,
+        padding: None,
+    };
+    let cosmos_msg = register_msg.to_cosmos_msg(code_hash, contract, None)?;
+
+    Ok(HandleResponse {
+        messages: vec![cosmos_msg],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::SetMedicine { status: Success })?),
+    })
+}
+
+fn set_accessory<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    contract: HumanAddr,
+    code_hash: String,
+    standard: TokenStandard,
+    stat: StatKind,
+    decay_percent: u64,
+) -> StdResult<HandleResponse> {
+    let config_state = config_read(&deps.storage).load()?;
+    check_if_admin(&config_state, &env.message.sender)?;
+
+    if decay_percent > MAX_DECAY_PERCENT {
+        return Err(StdError::GenericErr {
+            msg: format!("Decay percent can't be more than {}", MAX_DECAY_PERCENT),
+            backtrace: None,
+        });
+    }
+
+    let mut accessories = read_accessories(&deps.storage)?;
+    accessories.retain(|accessory| accessory.contract != contract);
+    accessories.push(Accessory {
+        contract: contract.clone(),
+        code_hash: code_hash.clone(),
+        standard,
+        stat,
+        decay_percent,
+    });
+    write_accessories(&mut deps.storage, &accessories)?;
+
+    // Items are delivered through the Receive callback of their standard
+    let cosmos_msg = match standard {
+        TokenStandard::Snip20 => FoodHandleMsg::RegisterReceive {
+            code_hash: env.contract_code_hash,
+            padding: None,
+        }
+        .to_cosmos_msg(code_hash, contract, None)?,
+        TokenStandard::Snip721 => Snip721HandleMsg::RegisterReceiveNft {
+            code_hash: env.contract_code_hash,
+            also_implements_batch_receive_nft: Some(false),
+            padding: None,
+        }
+        .to_cosmos_msg(code_hash, contract, None)?,
+    };
+
+    Ok(HandleResponse {
+        messages: vec![cosmos_msg],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::SetAccessory { status: Success })?),
+    })
+}
+
+fn remove_accessory<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    contract: HumanAddr,
+) -> StdResult<HandleResponse> {
+    let config_state = config_read(&deps.storage).load()?;
+    check_if_admin(&config_state, &env.message.sender)?;
+
+    let mut accessories = read_accessories(&deps.storage)?;
+    accessories.retain(|accessory| accessory.contract != contract);
+    write_accessories(&mut deps.storage, &accessories)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::RemoveAccessory {
+            status: Success,
+        })?),
+    })
+}
+
+fn set_banned_words<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    words: Vec<String>,
+) -> StdResult<HandleResponse> {
+    let config_state = config_read(&deps.storage).load()?;
+    check_if_admin(&config_state, &env.message.sender)?;
+
+    write_banned_words(&mut deps.storage, &words)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::SetBannedWords {
+            status: Success,
+        })?),
+    })
+}
+
+fn check_if_admin(config_state: &Config, account: &HumanAddr) -> StdResult<()> {
+    if &config_state.admin != account {
+        return Err(StdError::GenericErr {
+            msg: "This is an admin command. Admin commands can only be run from admin address"
+                .to_string(),
+            backtrace: None,
+        });
+    }
+
+    Ok(())
+}
+
+fn check_feeding_config(config_state: &Config) -> StdResult<()> {
+    if config_state.starvation_window == 0 || config_state.time_per_food == 0 {
+        return Err(StdError::GenericErr {
+            msg: "Starvation window and time per FOOD must be positive".to_string(),
+            backtrace: None,
+        });
+    }
+    if config_state.max_satiation < config_state.starvation_window {
+        return Err(StdError::GenericErr {
+            msg: "Max satiation can't be shorter than starvation window".to_string(),
+            backtrace: None,
+        });
+    }
+
+    Ok(())
+}
+
+pub fn query<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    msg: QueryMsg,
+) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Config {} => query_config(deps),
+        QueryMsg::Foods {} => query_foods(deps),
+        QueryMsg::Pet { pet_id, now } => query_pet(deps, pet_id, now),
+        QueryMsg::Pets {
+            owner,
+            now,
+            page,
+            page_size,
+        } => query_pets(deps, &owner, now, page.unwrap_or(0), page_size),
+        QueryMsg::Status { pet_id, now } => query_status(deps, pet_id, now),
+        QueryMsg::Caretakers { pet_id, now } => query_caretakers(deps, pet_id, now),
+        QueryMsg::Lineage { pet_id } => query_lineage(deps, pet_id),
+        QueryMsg::Health {
+            pet_id,
+            page,
+            page_size,
+        } => query_health(deps, pet_id, page.unwrap_or(0), page_size),
+        QueryMsg::Escrow { pet_id, now } => query_escrow(deps, pet_id, now),
+        QueryMsg::Graveyard {
+            owner,
+            page,
+            page_size,
+        } => query_graveyard(deps, owner, page.unwrap_or(0), page_size),
+        QueryMsg::Leaderboard {
+            board,
+            page,
+            page_size,
+        } => query_leaderboard(deps, board, page.unwrap_or(0), page_size),
+        QueryMsg::OwnerOf { token_id, .. } => query_owner_of(deps, &token_id),
+        QueryMsg::NftInfo { token_id } => query_nft_info(deps, &token_id),
+        QueryMsg::PrivateMetadata { viewer: None, .. } => {
+            Err(StdError::Unauthorized { backtrace: None })
+        }
+        _ => authenticated_queries(deps, msg),
+    }
+}
+
+pub fn authenticated_queries<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    msg: QueryMsg,
+) -> StdResult<Binary> {
+    let (addresses, key) = msg.get_validation_params();
+
+    for address in addresses {
+        let canonical_addr = deps.api.canonical_address(address)?;
+
+        // Checking the key will take significant time. We don't want to exit immediately if it isn't set
+        // in a way which will allow to time the command and determine if a viewing key doesn't exist
+        let expected_key = read_viewing_key(&deps.storage, &canonical_addr)
+            .unwrap_or_else(|| vec![0u8; VIEWING_KEY_SIZE]);
+
+        if key.check_viewing_key(expected_key.as_slice()) {
+            return match msg {
+                QueryMsg::FeedingHistory {
+                    pet_id,
+                    address,
+                    page,
+                    page_size,
+                    ..
+                } => query_feeding_history(deps, pet_id, &address, page.unwrap_or(0), page_size),
+                QueryMsg::PrivateMetadata {
+                    token_id,
+                    viewer: Some(viewer),
+                } => query_private_metadata(deps, &token_id, &viewer.address),
+                _ => panic!("This query type does not require authentication"),
+            };
+        }
+    }
+
+    to_binary(&QueryAnswer::ViewingKeyError {
+        msg: "Wrong viewing key for this address or viewing key not set".to_string(),
+    })
+}
+
+fn pet_info(pet: State, now: u64) -> PetInfo {
+    PetInfo {
+        id: pet.id,
+        is_alive: pet.is_alive(now),
+        full_until: pet.deadline(now),
+        name: pet.name,
+        owner: pet.owner,
+        traits: pet.traits,
+    }
+}
+
+fn query_config<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>) -> StdResult<Binary> {
+    let config_state = config_read(&deps.storage).load()?;
+
+    to_binary(&QueryAnswer::Config {
+        revival_cost: revival_cost(&config_state),
+        admin: config_state.admin,
+        food_token_addr: config_state.food_token_addr,
+        starvation_window: config_state.starvation_window,
+        time_per_food: config_state.time_per_food,
+        max_satiation: config_state.max_satiation,
+        happiness_decay: config_state.happiness_decay,
+        cleanliness_decay: config_state.cleanliness_decay,
+        energy_decay: config_state.energy_decay,
+        revival_enabled: config_state.revival_enabled,
+        settle_bounty: config_state.settle_bounty,
+        pool: config_state.pool,
+        hibernation_fee: config_state.hibernation_fee,
+        max_hibernation: config_state.max_hibernation,
+        breeding_fee: config_state.breeding_fee,
+        medicine_addr: config_state.medicine.map(|medicine| medicine.contract),
+    })
+}
+
+fn query_foods<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>) -> StdResult<Binary> {
+    let config_state = config_read(&deps.storage).load()?;
+
+    // Primary FOOD is listed first
+    let mut foods = vec![config_state.primary_food()];
+    foods.extend(read_foods(&deps.storage)?);
+
+    to_binary(&QueryAnswer::Foods { foods })
+}
+
+/// Loads the pet as it is at `now`, including escrow debits nobody has settled yet.
+fn load_pet_at<S: Storage>(storage: &S, pet_id: u64, now: u64) -> StdResult<State> {
+    let config_state = config_read(storage).load()?;
+    let mut pet = load_pet(storage, pet_id)?;
+    debit_escrow(&config_state, &mut pet, now);
+    Ok(pet)
+}
+
+fn query_pet<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    pet_id: u64,
+    now: u64,
+) -> StdResult<Binary> {
+    let pet = load_pet_at(&deps.storage, pet_id, now)?;
+
+    to_binary(&QueryAnswer::Pet {
+        pet: pet_info(pet, now),
+    })
+}
+
+fn query_pets<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    owner: &HumanAddr,
+    now: u64,
+    page: u32,
+    page_size: u32,
+) -> StdResult<Binary> {
+    let owner = deps.api.canonical_address(owner)?;
+
+    // Pets are listed from the oldest one
+    let pets: StdResult<Vec<PetInfo>> = read_owner_pets(&deps.storage, &owner)?
+        .into_iter()
+        .skip((page * page_size) as _)
+        .take(page_size as _)
+        .map(|pet_id| load_pet_at(&deps.storage, pet_id, now).map(|pet| pet_info(pet, now)))
+        .collect();
+
+    to_binary(&QueryAnswer::Pets { pets: pets? })
+}
+
+fn query_status<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    pet_id: u64,
+    now: u64,
+) -> StdResult<Binary> {
+    let config_state = config_read(&deps.storage).load()?;
+    let pet = load_pet_at(&deps.storage, pet_id, now)?;
+
+    let status = PetStatus {
+        id: pet.id,
+        is_alive: pet.is_alive(now),
+        seconds_left: pet.seconds_left(now),
+        died_at: pet.died_at(now),
+        stage: pet.stage_at(now),
+        wellbeing: wellbeing(&config_state, &pet, now),
+        deaths: pet.deaths,
+        full_until: pet.deadline(now),
+        hibernating_until: pet
+            .hibernation
+            .as_ref()
+            .filter(|_| pet.is_hibernating(now))
+            .map(|hibernation| hibernation.until),
+        ill_since: pet.ill_since,
+        inventory: pet.inventory,
+        name: pet.name,
+        bio: pet.bio,
+        avatar_uri: pet.avatar_uri,
+        owner: pet.owner,
+        traits: pet.traits,
+        pending_owner: pet.pending_owner,
+        born_at: pet.born_at,
+        food_eaten: pet.food_eaten,
+        feed_count: pet.feed_count,
+        streak: pet.streak,
+        food_token_addr: config_state.food_token_addr,
+    };
+
+    to_binary(&QueryAnswer::Status { status })
+}
+
+fn query_caretakers<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    pet_id: u64,
+    now: u64,
+) -> StdResult<Binary> {
+    // Make sure the pet exists, so unknown ids are not reported as having no caretakers
+    load_pet(&deps.storage, pet_id)?;
+
+    let caretakers = read_caretakers(&deps.storage, pet_id)?
+        .into_iter()
+        .filter(|caretaker| !caretaker.is_expired(now))
+        .collect();
+
+    to_binary(&QueryAnswer::Caretakers { caretakers })
+}
+
+fn query_lineage<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    pet_id: u64,
+) -> StdResult<Binary> {
+    let pet = load_pet(&deps.storage, pet_id)?;
+
+    to_binary(&QueryAnswer::Lineage {
+        pet_id,
+        generation: pet.generation,
+        parents: pet.parents,
+        children: read_children(&deps.storage, pet_id)?,
+    })
+}
+
+fn query_health<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    pet_id: u64,
+    page: u32,
+    page_size: u32,
+) -> StdResult<Binary> {
+    let pet = load_pet(&deps.storage, pet_id)?;
+    let illnesses = get_illnesses(&deps.storage, pet_id, page, page_size)?;
+
+    to_binary(&QueryAnswer::Health {
+        ill_since: pet.ill_since,
+        illnesses,
+    })
+}
+
+fn query_escrow<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    pet_id: u64,
+    now: u64,
+) -> StdResult<Binary> {
+    let pet = load_pet_at(&deps.storage, pet_id, now)?;
+
+    to_binary(&QueryAnswer::Escrow { amount: pet.escrow })
+}
+
+fn query_graveyard<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    owner: Option<HumanAddr>,
+    page: u32,
+    page_size: u32,
+) -> StdResult<Binary> {
+    let memorials = match owner {
+        Some(owner) => {
+            let owner = deps.api.canonical_address(&owner)?;
+            get_owner_memorials(&deps.storage, &owner, page, page_size)?
+        }
+        None => get_memorials(&deps.storage, page, page_size)?,
+    };
+
+    to_binary(&QueryAnswer::Graveyard { memorials })
+}
+
+fn leaderboard_score(pet: &State, board: Board, now: u64) -> u128 {
+    match board {
+        Board::Age => pet.age(now) as u128,
+        Board::Lifespan => pet.lifespan(now) as u128,
+        Board::FoodEaten => pet.food_eaten.u128(),
+        Board::Streak => pet.best_streak as u128,
+    }
+}
+
+fn board_key(board: Board) -> &'static [u8] {
+    match board {
+        Board::Age => b"age",
+        Board::Lifespan => b"lifespan",
+        Board::FoodEaten => b"food_eaten",
+        Board::Streak => b"streak",
+    }
+}
+
+/// Brings the scores of a listed pet up to date, as of `now`.
+fn update_leaderboards<S: Storage>(storage: &mut S, pet: &State, now: u64) -> StdResult<()> {
+    if !pet.listed {
+        return Ok(());
+    }
+
+    for board in BOARDS.iter() {
+        let score = Uint128(leaderboard_score(pet, *board, now));
+        update_leaderboard(storage, board_key(*board), pet.id, Some(score))?;
+    }
+    Ok(())
+}
+
+fn remove_from_leaderboards<S: Storage>(storage: &mut S, pet_id: u64) -> StdResult<()> {
+    for board in BOARDS.iter() {
+        update_leaderboard(storage, board_key(*board), pet_id, None)?;
+    }
+    Ok(())
+}
+
+fn query_leaderboard<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    board: Board,
+    page: u32,
+    page_size: u32,
+) -> StdResult<Binary> {
+    if page_size == 0 || page_size > MAX_LEADERBOARD_PAGE {
+        return Err(StdError::GenericErr {
+            msg: format!("Page size must be 1 to {}", MAX_LEADERBOARD_PAGE),
+            backtrace: None,
+        });
+    }
+
+    let skip = page.saturating_mul(page_size);
+    let entries = read_leaderboard(&deps.storage, board_key(board))?
+        .into_iter()
+        .enumerate()
+        .skip(skip as _)
+        .take(page_size as _)
+        .map(|(index, entry)| {
+            let pet = load_pet(&deps.storage, entry.pet_id)?;
+            Ok(LeaderboardEntry {
+                rank: index as u32 + 1,
+                pet_id: pet.id,
+                name: pet.name,
+                owner: pet.owner,
+                score: entry.score,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    to_binary(&QueryAnswer::Leaderboard { entries })
+}
+
+fn query_owner_of<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    token_id: &str,
+) -> StdResult<Binary> {
+    let pet = load_pet(&deps.storage, parse_token_id(token_id)?)?;
+
+    to_binary(&QueryAnswer::OwnerOf {
+        owner: pet.owner,
+        approvals: vec![],
+    })
+}
+
+fn trait_attribute(trait_type: &str, value: String) -> Trait {
+    Trait {
+        display_type: None,
+        trait_type: Some(trait_type.to_string()),
+        value,
+    }
+}
+
+/// Public metadata shows how the pet looks, private one how it's doing.
+fn pet_metadata(pet: State, private: bool) -> Extension {
+    let attributes = if private {
+        vec![
+            trait_attribute("dna", pet.dna.to_base64()),
+            trait_attribute("metabolism", pet.traits.metabolism.to_string()),
+            trait_attribute("born_at", pet.born_at.to_string()),
+            trait_attribute("food_eaten", pet.food_eaten.to_string()),
+            trait_attribute("feed_count", pet.feed_count.to_string()),
+            trait_attribute("streak", pet.streak.to_string()),
+            trait_attribute("deaths", pet.deaths.to_string()),
+        ]
+    } else {
+        vec![
+            trait_attribute("species", format!("{:?}", pet.traits.species)),
+            trait_attribute("color", format!("{:?}", pet.traits.color)),
+            trait_attribute("temperament", format!("{:?}", pet.traits.temperament)),
+            trait_attribute("stage", pet.stage.as_str().to_string()),
+            trait_attribute("generation", pet.generation.to_string()),
+        ]
+    };
+
+    Extension {
+        name: Some(pet.name),
+        description: pet.bio,
+        image: pet.avatar_uri,
+        attributes: Some(attributes),
+    }
+}
+
+fn query_nft_info<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    token_id: &str,
+) -> StdResult<Binary> {
+    let pet = load_pet(&deps.storage, parse_token_id(token_id)?)?;
+
+    to_binary(&QueryAnswer::NftInfo {
+        token_uri: None,
+        extension: Some(pet_metadata(pet, false)),
+    })
+}
+
+fn query_private_metadata<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    token_id: &str,
+    viewer: &HumanAddr,
+) -> StdResult<Binary> {
+    let pet = load_pet(&deps.storage, parse_token_id(token_id)?)?;
+    if &pet.owner != viewer {
+        return Err(StdError::Unauthorized { backtrace: None });
+    }
+
+    to_binary(&QueryAnswer::PrivateMetadata {
+        token_uri: None,
+        extension: Some(pet_metadata(pet, true)),
+    })
+}
+
+fn query_feeding_history<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    pet_id: u64,
+    address: &HumanAddr,
+    page: u32,
+    page_size: u32,
+) -> StdResult<Binary> {
+    // History is private to the pet owner
+    let pet = load_pet(&deps.storage, pet_id)?;
+    if &pet.owner != address {
+        return Err(StdError::Unauthorized { backtrace: None });
+    }
+
+    let events = get_feed_events(&deps.api, &deps.storage, pet_id, page, page_size)?;
+
+    to_binary(&QueryAnswer::FeedingHistory { events })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::msg::ViewerInfo;
+    use crate::state::{Color, Species, Temperament, Traits, LEADERBOARD_SIZE, STREAK_INTERVAL};
+    use cosmwasm_std::testing::*;
+
+    const BORN_AT: u64 = 1_571_797_419;
+
+    // Helper functions
+
+    /// Instantiates the contract and creates a single pet (id 1) for "owner".
+    fn init_helper() -> Extern<MockStorage, MockApi, MockQuerier> {
+        let mut deps = mock_dependencies(20, &[]);
+
+        let init_msg = InitMsg {
+            admin: None,
+            prng_seed: Binary::from("lolz fun yay".as_bytes()),
+            food_token_code_hash: "food_hash".to_string(),
+            food_token_addr: HumanAddr("food".to_string()),
+            banned_words: None,
+            starvation_window: None,
+            time_per_food: None,
+            max_satiation: None,
+            happiness_decay: None,
+            cleanliness_decay: None,
+            energy_decay: None,
+            revival_enabled: None,
+            revival_multiplier: None,
+            settle_bounty: None,
+            hibernation_fee: None,
+            max_hibernation: None,
+            breeding_fee: None,
+        };
+        init(&mut deps, mock_env("admin", &[]), init_msg).unwrap();
+
+        let pet_id = create_pet_helper(&mut deps, "owner", "Rex");
+        assert_eq!(pet_id, 1);
+
+        deps
+    }
+
+    fn create_pet_helper(
+        deps: &mut Extern<MockStorage, MockApi, MockQuerier>,
+        owner: &str,
+        name: &str,
+    ) -> u64 {
+        let mut env = mock_env(owner, &[]);
+        env.block.time = BORN_AT;
+
+        let handle_msg = HandleMsg::CreatePet {
+            name: name.to_string(),
+        };
+        let result = handle(deps, env, handle_msg).unwrap();
+        match from_binary(&result.data.unwrap()).unwrap() {
+            HandleAnswer::CreatePet { pet_id } => pet_id,
+            _ => panic!("Unexpected handle answer"),
+        }
+    }
+
+    /// Gives all pets created so far an average metabolism, for tests which expect
+    /// countdowns to follow the configuration exactly.
+    fn average_metabolism_helper(deps: &mut Extern<MockStorage, MockApi, MockQuerier>) {
+        let config_state = config_read(&deps.storage).load().unwrap();
+        for pet_id in 1..=config_state.pet_count {
+            let mut pet = load_pet(&deps.storage, pet_id).unwrap();
+            pet.traits.metabolism = 100;
+            pet.full_until = pet.born_at + config_state.starvation_window;
+            save_pet(&mut deps.storage, &pet).unwrap();
+        }
+    }
+
+    fn receive_helper(
+        deps: &mut Extern<MockStorage, MockApi, MockQuerier>,
+        time: u64,
+        from: &str,
+        amount: u128,
+        receive_msg: ReceiveMsg,
+    ) -> StdResult<HandleResponse> {
+        // Receive callback is always sent by the FOOD contract
+        let mut env = mock_env("food", &[]);
+        env.block.time = time;
+
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr(from.to_string()),
+            from: HumanAddr(from.to_string()),
+            amount: Uint128(amount),
+            msg: Some(to_binary(&receive_msg).unwrap()),
+        };
+        handle(deps, env, handle_msg)
+    }
+
+    fn feed_helper(
+        deps: &mut Extern<MockStorage, MockApi, MockQuerier>,
+        time: u64,
+        from: &str,
+        pet_id: u64,
+        amount: u128,
+    ) -> StdResult<HandleResponse> {
+        receive_helper(deps, time, from, amount, ReceiveMsg::Feed { pet_id })
+    }
+
+    fn query_pet_helper(
+        deps: &Extern<MockStorage, MockApi, MockQuerier>,
+        pet_id: u64,
+        now: u64,
+    ) -> PetInfo {
+        let query_msg = QueryMsg::Pet { pet_id, now };
+        match from_binary(&query(deps, query_msg).unwrap()).unwrap() {
+            QueryAnswer::Pet { pet } => pet,
+            _ => panic!("Unexpected query answer"),
+        }
+    }
+
+    fn extract_error_msg<T>(error: StdResult<T>) -> String {
+        match error {
+            Ok(_) => panic!("An error was expected"),
+            Err(StdError::GenericErr { msg, .. }) => msg,
+            Err(err) => panic!("Unexpected error: {}", err),
+        }
+    }
+
+    // Init tests
+
+    #[test]
+    fn test_init_sanity() {
+        let mut deps = mock_dependencies(20, &[]);
+        let env = mock_env("admin", &[]);
+        let init_msg = InitMsg {
+            admin: None,
+            prng_seed: Binary::from("lolz fun yay".as_bytes()),
+            food_token_code_hash: "food_hash".to_string(),
+            food_token_addr: HumanAddr("food".to_string()),
+            banned_words: None,
+            starvation_window: None,
+            time_per_food: None,
+            max_satiation: None,
+            happiness_decay: None,
+            cleanliness_decay: None,
+            energy_decay: None,
+            revival_enabled: None,
+            revival_multiplier: None,
+            settle_bounty: None,
+            hibernation_fee: None,
+            max_hibernation: None,
+            breeding_fee: None,
+        };
+        let init_result = init(&mut deps, env.clone(), init_msg).unwrap();
+
+        let register_msg = FoodHandleMsg::RegisterReceive {
+            code_hash: env.contract_code_hash,
+            padding: None,
+        }
+        .to_cosmos_msg("food_hash".to_string(), HumanAddr("food".to_string()), None)
+        .unwrap();
+        assert_eq!(init_result.messages, vec![register_msg]);
+
+        let config_state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(config_state.admin, HumanAddr("admin".to_string()));
+        assert_eq!(config_state.food_token_addr, HumanAddr("food".to_string()));
+        assert_eq!(config_state.starvation_window, FOUR_HOURS);
+        assert_eq!(config_state.time_per_food, FOUR_HOURS);
+        assert_eq!(config_state.max_satiation, FOUR_HOURS);
+        assert_eq!(config_state.pet_count, 0);
+        assert_eq!(
+            config_state.prng_seed,
+            sha_256("lolz fun yay".to_owned().as_bytes())
+        );
+        assert_eq!(config_state.prng_nonce, 0);
+    }
+
+    #[test]
+    fn test_init_invalid_feeding_config() {
+        let mut deps = mock_dependencies(20, &[]);
+        let init_msg = InitMsg {
+            admin: None,
+            prng_seed: Binary::from("lolz fun yay".as_bytes()),
+            food_token_code_hash: "food_hash".to_string(),
+            food_token_addr: HumanAddr("food".to_string()),
+            banned_words: None,
+            starvation_window: Some(FOUR_HOURS),
+            time_per_food: None,
+            max_satiation: Some(FOUR_HOURS - 1),
+            happiness_decay: None,
+            cleanliness_decay: None,
+            energy_decay: None,
+            revival_enabled: None,
+            revival_multiplier: None,
+            settle_bounty: None,
+            hibernation_fee: None,
+            max_hibernation: None,
+            breeding_fee: None,
+        };
+        let init_result = init(&mut deps, mock_env("admin", &[]), init_msg);
+        let error = extract_error_msg(init_result);
+        assert_eq!(
+            error,
+            "Max satiation can't be shorter than starvation window"
+        );
+    }
+
+    // Handle tests
+
+    #[test]
+    fn test_create_pet() {
+        let mut deps = init_helper();
+
+        let pet_id = create_pet_helper(&mut deps, "owner", "Fluffy");
+        assert_eq!(pet_id, 2);
+        let pet_id = create_pet_helper(&mut deps, "other", "Spot");
+        assert_eq!(pet_id, 3);
+
+        let pet = query_pet_helper(&deps, 2, BORN_AT);
+        assert_eq!(pet.name, "Fluffy");
+        assert_eq!(pet.owner, HumanAddr("owner".to_string()));
+        assert_eq!(
+            pet.full_until,
+            BORN_AT + pet.traits.satiation_time(FOUR_HOURS)
+        );
+        assert!(pet.is_alive);
+
+        let owner = deps
+            .api
+            .canonical_address(&HumanAddr("owner".to_string()))
+            .unwrap();
+        assert_eq!(read_owner_pets(&deps.storage, &owner).unwrap(), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_set_metadata() {
+        let mut deps = init_helper();
+
+        let handle_msg = HandleMsg::CreatePet {
+            name: "Al".to_string(),
+        };
+        let result = handle(&mut deps, mock_env("owner", &[]), handle_msg);
+        assert!(result.is_err());
+
+        let handle_msg = HandleMsg::SetBannedWords {
+            words: vec!["Poop".to_string()],
+        };
+        let result = handle(&mut deps, mock_env("owner", &[]), handle_msg.clone());
+        assert!(result.is_err());
+        handle(&mut deps, mock_env("admin", &[]), handle_msg).unwrap();
+        let handle_msg = HandleMsg::CreatePet {
+            name: "Mr. POOPY".to_string(),
+        };
+        let result = handle(&mut deps, mock_env("owner", &[]), handle_msg);
+        assert!(result.is_err());
+
+        let set_metadata = |name: Option<&str>, bio: Option<&str>, avatar_uri: Option<&str>| {
+            HandleMsg::SetMetadata {
+                pet_id: 1,
+                name: name.map(str::to_string),
+                bio: bio.map(str::to_string),
+                avatar_uri: avatar_uri.map(str::to_string),
+                padding: None,
+            }
+        };
+
+        // Only the owner can edit the pet
+        let handle_msg = set_metadata(Some("Max"), None, None);
+        let result = handle(&mut deps, mock_env("other", &[]), handle_msg);
+        assert!(result.is_err());
+
+        let handle_msg = set_metadata(Some("Max"), Some("Good boy"), Some("ipfs://max"));
+        let result = handle(&mut deps, mock_env("owner", &[]), handle_msg).unwrap();
+        let answer: HandleAnswer = from_binary(&result.data.unwrap()).unwrap();
+        assert_eq!(answer, HandleAnswer::SetMetadata { status: Success });
+        let pet = load_pet(&deps.storage, 1).unwrap();
+        assert_eq!(pet.name, "Max");
+        assert_eq!(pet.bio, Some("Good boy".to_string()));
+        assert_eq!(pet.avatar_uri, Some("ipfs://max".to_string()));
+
+        // Nothing is saved if any of the fields is invalid
+        let long_bio = "a".repeat(MAX_BIO_LEN + 1);
+        let long_uri = "a".repeat(MAX_AVATAR_URI_LEN + 1);
+        for handle_msg in &[
+            set_metadata(Some(&"a".repeat(31)), None, None),
+            set_metadata(Some("Rex"), Some("Loves poop"), None),
+            set_metadata(Some("Rex"), Some(&long_bio), None),
+            set_metadata(Some("Rex"), None, Some(&long_uri)),
+        ] {
+            let result = handle(&mut deps, mock_env("owner", &[]), handle_msg.clone());
+            assert!(result.is_err());
+        }
+        let pet = load_pet(&deps.storage, 1).unwrap();
+        assert_eq!(pet.name, "Max");
+
+        // Empty strings clear the fields, unset ones are left alone
+        let handle_msg = set_metadata(None, Some(""), None);
+        handle(&mut deps, mock_env("owner", &[]), handle_msg).unwrap();
+        let query_msg = QueryMsg::Status {
+            pet_id: 1,
+            now: BORN_AT,
+        };
+        let status = match from_binary(&query(&deps, query_msg).unwrap()).unwrap() {
+            QueryAnswer::Status { status } => status,
+            _ => panic!("Unexpected query answer"),
+        };
+        assert_eq!(status.name, "Max");
+        assert_eq!(status.bio, None);
+        assert_eq!(status.avatar_uri, Some("ipfs://max".to_string()));
+    }
+
+    #[test]
+    fn test_pet_traits() {
+        let traits = Traits::from_dna(&[6, 9, 3, 40]);
+        assert_eq!(traits.species, Species::Dog);
+        assert_eq!(traits.color, Color::Orange);
+        assert_eq!(traits.temperament, Temperament::Shy);
+        assert_eq!(traits.metabolism, 120);
+        assert_eq!(traits.satiation_time(FOUR_HOURS), FOUR_HOURS * 100 / 120);
+
+        // Traits are decoded from the random DNA pet is born with
+        let mut deps = init_helper();
+        let mut env = mock_env("owner", &[]);
+        env.block.time = BORN_AT;
+        let handle_msg = HandleMsg::CreatePet {
+            name: "Lucky".to_string(),
+        };
+        handle(&mut deps, env, handle_msg).unwrap();
+
+        let pet = load_pet(&deps.storage, 2).unwrap();
+        assert_eq!(pet.traits, Traits::from_dna(pet.dna.as_slice()));
+        assert_eq!(
+            pet.full_until,
+            BORN_AT + pet.traits.satiation_time(FOUR_HOURS)
+        );
+        assert_eq!(query_pet_helper(&deps, 2, BORN_AT).traits, pet.traits);
+
+        // Metabolism also scales the time every FOOD buys, and the satiation cap
+        let handle_msg = HandleMsg::SetFeedingConfig {
+            starvation_window: None,
+            time_per_food: None,
+            max_satiation: Some(3 * FOUR_HOURS),
+        };
+        handle(&mut deps, mock_env("admin", &[]), handle_msg).unwrap();
+        for (pet_id, metabolism, window) in &[(1, 120, 12_000), (2, 80, 18_000)] {
+            let mut pet = load_pet(&deps.storage, *pet_id).unwrap();
+            pet.traits.metabolism = *metabolism;
+            pet.full_until = BORN_AT + pet.traits.satiation_time(FOUR_HOURS);
+            save_pet(&mut deps.storage, &pet).unwrap();
+
+            feed_helper(&mut deps, BORN_AT, "owner", *pet_id, 1).unwrap();
+            let pet = load_pet(&deps.storage, *pet_id).unwrap();
+            assert_eq!(pet.full_until, BORN_AT + 2 * window);
+            feed_helper(&mut deps, BORN_AT, "owner", *pet_id, 5).unwrap();
+            let pet = load_pet(&deps.storage, *pet_id).unwrap();
+            assert_eq!(pet.full_until, BORN_AT + 3 * window);
+        }
+    }
+
+    #[test]
+    fn test_receive_from_unknown_token() {
+        let mut deps = init_helper();
+
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("owner".to_string()),
+            from: HumanAddr("owner".to_string()),
+            amount: Uint128(1),
+            msg: Some(to_binary(&ReceiveMsg::Feed { pet_id: 1 }).unwrap()),
+        };
+        let handle_result = handle(&mut deps, mock_env("fake_food", &[]), handle_msg);
+        let error = extract_error_msg(handle_result);
+        assert_eq!(error, "Only FOOD tokens are accepted");
+    }
+
+    #[test]
+    fn test_receive_callback() {
+        let mut deps = init_helper();
+        let receive_msg = |msg| HandleMsg::Receive {
+            sender: HumanAddr("owner".to_string()),
+            from: HumanAddr("owner".to_string()),
+            amount: Uint128(3),
+            msg,
+        };
+
+        let handle_result = handle(&mut deps, mock_env("food", &[]), receive_msg(None));
+        let error = extract_error_msg(handle_result);
+        assert_eq!(error, "Receive message is missing");
+
+        // Owner can't skip the transfer by calling Receive directly
+        let feed_msg = Some(to_binary(&ReceiveMsg::Feed { pet_id: 1 }).unwrap());
+        let handle_result = handle(
+            &mut deps,
+            mock_env("owner", &[]),
+            receive_msg(feed_msg.clone()),
+        );
+        let error = extract_error_msg(handle_result);
+        assert_eq!(error, "Only FOOD tokens are accepted");
+        let pet = load_pet(&deps.storage, 1).unwrap();
+        assert_eq!(pet.food_eaten, Uint128::zero());
+        assert_eq!(pet.feed_count, 0);
+
+        // Callback of the FOOD contract feeds the pet on behalf of `from`
+        let result = handle(&mut deps, mock_env("food", &[]), receive_msg(feed_msg)).unwrap();
+        assert_eq!(result.log[0], log("action", "feed"));
+        let pet = load_pet(&deps.storage, 1).unwrap();
+        assert_eq!(pet.food_eaten, Uint128(3));
+        assert_eq!(pet.feed_count, 1);
+    }
+
+    #[test]
+    fn test_feed_not_owner() {
+        let mut deps = init_helper();
+
+        let handle_result = feed_helper(&mut deps, BORN_AT + 60, "stranger", 1, 1);
+        match handle_result {
+            Err(StdError::Unauthorized { .. }) => {}
+            _ => panic!("Expected Unauthorized error"),
+        }
+    }
+
+    #[test]
+    fn test_wellbeing() {
+        let mut deps = init_helper();
+        average_metabolism_helper(&mut deps);
+        let hour = 60 * 60;
+
+        let care_helper = |deps: &mut Extern<MockStorage, MockApi, MockQuerier>,
+                           time: u64,
+                           sender: &str,
+                           handle_msg: HandleMsg| {
+            let mut env = mock_env(sender, &[]);
+            env.block.time = time;
+            handle(deps, env, handle_msg)
+        };
+
+        let handle_result = care_helper(
+            &mut deps,
+            BORN_AT + hour,
+            "stranger",
+            HandleMsg::Clean { pet_id: 1 },
+        );
+        match handle_result {
+            Err(StdError::Unauthorized { .. }) => {}
+            _ => panic!("Expected Unauthorized error"),
+        }
+
+        // Neglected pet eats less efficiently
+        let time = BORN_AT + 3 * hour;
+        let pet = load_pet(&deps.storage, 1).unwrap();
+        let config_state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(
+            feeding_efficiency(&config_state, &pet, time),
+            50 + (88 + 91 + 85) * 50 / 300
+        );
+
+        care_helper(&mut deps, time, "owner", HandleMsg::Clean { pet_id: 1 }).unwrap();
+        care_helper(&mut deps, time, "owner", HandleMsg::Sleep { pet_id: 1 }).unwrap();
+        let result = care_helper(
+            &mut deps,
+            time,
+            "owner",
+            HandleMsg::Play {
+                pet_id: 1,
+                game: None,
+            },
+        )
+        .unwrap();
+        assert_eq!(result.log, vec![log("action", "play"), log("pet_id", 1)]);
+
+        let pet = load_pet(&deps.storage, 1).unwrap();
+        assert_eq!(pet.happiness.value_at(time, 4), 100);
+        assert_eq!(pet.cleanliness.value_at(time, 3), 100);
+        assert_eq!(pet.energy.value_at(time, 5), 90);
+
+        // Energy runs out after enough playing
+        for _ in 0..9 {
+            care_helper(
+                &mut deps,
+                time,
+                "owner",
+                HandleMsg::Play {
+                    pet_id: 1,
+                    game: None,
+                },
+            )
+            .unwrap();
+        }
+        let handle_result = care_helper(
+            &mut deps,
+            time,
+            "owner",
+            HandleMsg::Play {
+                pet_id: 1,
+                game: None,
+            },
+        );
+        let error = extract_error_msg(handle_result);
+        assert_eq!(error, "Pet is too tired to play");
+
+        let handle_result = care_helper(
+            &mut deps,
+            BORN_AT + FOUR_HOURS + 1,
+            "owner",
+            HandleMsg::Sleep { pet_id: 1 },
+        );
+        let error = extract_error_msg(handle_result);
+        assert_eq!(error, "Pet 1 is dead");
+    }
+
+    #[test]
+    fn test_transfer_ownership() {
+        let mut deps = init_helper();
+
+        let handle_msg = HandleMsg::AddCaretaker {
+            pet_id: 1,
+            address: HumanAddr("friend".to_string()),
+            expiration: None,
+        };
+        handle(&mut deps, mock_env("owner", &[]), handle_msg).unwrap();
+
+        let handle_msg = HandleMsg::TransferOwnership {
+            pet_id: 1,
+            new_owner: HumanAddr("buyer".to_string()),
+        };
+        let handle_result = handle(&mut deps, mock_env("friend", &[]), handle_msg.clone());
+        match handle_result {
+            Err(StdError::Unauthorized { .. }) => {}
+            _ => panic!("Expected Unauthorized error"),
+        }
+        let result = handle(&mut deps, mock_env("owner", &[]), handle_msg).unwrap();
+        assert_eq!(
+            result.log,
+            vec![
+                log("action", "transfer_ownership"),
+                log("pet_id", 1),
+                log("from", "owner"),
+                log("to", "buyer"),
+            ]
+        );
+
+        // Pet stays with the owner until the transfer is accepted
+        let query_msg = QueryMsg::Status {
+            pet_id: 1,
+            now: BORN_AT,
+        };
+        let status = match from_binary(&query(&deps, query_msg).unwrap()).unwrap() {
+            QueryAnswer::Status { status } => status,
+            _ => panic!("Unexpected query answer"),
+        };
+        assert_eq!(status.owner, HumanAddr("owner".to_string()));
+        assert_eq!(status.pending_owner, Some(HumanAddr("buyer".to_string())));
+
+        let handle_msg = HandleMsg::AcceptOwnership { pet_id: 1 };
+        let handle_result = handle(&mut deps, mock_env("stranger", &[]), handle_msg.clone());
+        match handle_result {
+            Err(StdError::Unauthorized { .. }) => {}
+            _ => panic!("Expected Unauthorized error"),
+        }
+        let result = handle(&mut deps, mock_env("buyer", &[]), handle_msg).unwrap();
+        assert_eq!(result.log[0], log("action", "accept_ownership"));
+
+        let pet = load_pet(&deps.storage, 1).unwrap();
+        assert_eq!(pet.owner, HumanAddr("buyer".to_string()));
+        assert_eq!(pet.pending_owner, None);
+        assert!(read_caretakers(&deps.storage, 1).unwrap().is_empty());
+
+        let previous_owner = deps
+            .api
+            .canonical_address(&HumanAddr("owner".to_string()))
+            .unwrap();
+        let new_owner = deps
+            .api
+            .canonical_address(&HumanAddr("buyer".to_string()))
+            .unwrap();
+        assert!(read_owner_pets(&deps.storage, &previous_owner)
+            .unwrap()
+            .is_empty());
+        assert_eq!(read_owner_pets(&deps.storage, &new_owner).unwrap(), vec![1]);
+
+        let handle_result = feed_helper(&mut deps, BORN_AT + 60, "owner", 1, 1);
+        match handle_result {
+            Err(StdError::Unauthorized { .. }) => {}
+            _ => panic!("Expected Unauthorized error"),
+        }
+        feed_helper(&mut deps, BORN_AT + 60, "buyer", 1, 1).unwrap();
+    }
+
+    #[test]
+    fn test_cancel_ownership_transfer() {
+        let mut deps = init_helper();
+
+        let handle_msg = HandleMsg::CancelOwnershipTransfer { pet_id: 1 };
+        let handle_result = handle(&mut deps, mock_env("owner", &[]), handle_msg.clone());
+        let error = extract_error_msg(handle_result);
+        assert_eq!(error, "Pet 1 has no pending transfer");
+
+        let transfer_msg = HandleMsg::TransferOwnership {
+            pet_id: 1,
+            new_owner: HumanAddr("buyer".to_string()),
+        };
+        handle(&mut deps, mock_env("owner", &[]), transfer_msg).unwrap();
+
+        let handle_result = handle(&mut deps, mock_env("buyer", &[]), handle_msg.clone());
+        match handle_result {
+            Err(StdError::Unauthorized { .. }) => {}
+            _ => panic!("Expected Unauthorized error"),
+        }
+        let result = handle(&mut deps, mock_env("owner", &[]), handle_msg).unwrap();
+        assert_eq!(
+            result.log,
+            vec![
+                log("action", "cancel_ownership_transfer"),
+                log("pet_id", 1),
+                log("to", "buyer"),
+            ]
+        );
+        assert_eq!(load_pet(&deps.storage, 1).unwrap().pending_owner, None);
+
+        // Cancelled offer can't be accepted anymore
+        let accept_msg = HandleMsg::AcceptOwnership { pet_id: 1 };
+        let handle_result = handle(&mut deps, mock_env("buyer", &[]), accept_msg);
+        match handle_result {
+            Err(StdError::Unauthorized { .. }) => {}
+            _ => panic!("Expected Unauthorized error"),
+        }
+        let pet = load_pet(&deps.storage, 1).unwrap();
+        assert_eq!(pet.owner, HumanAddr("owner".to_string()));
+    }
+
+    #[test]
+    fn test_feed_by_caretaker() {
+        let mut deps = init_helper();
+
+        let handle_msg = HandleMsg::AddCaretaker {
+            pet_id: 1,
+            address: HumanAddr("friend".to_string()),
+            expiration: Some(BORN_AT + 60),
+        };
+        let handle_result = handle(&mut deps, mock_env("friend", &[]), handle_msg.clone());
+        match handle_result {
+            Err(StdError::Unauthorized { .. }) => {}
+            _ => panic!("Expected Unauthorized error"),
+        }
+        handle(&mut deps, mock_env("owner", &[]), handle_msg).unwrap();
+
+        let result = feed_helper(&mut deps, BORN_AT + 60, "friend", 1, 1).unwrap();
+        assert_eq!(result.log[0], log("action", "feed"));
+
+        // Permission expires
+        let handle_result = feed_helper(&mut deps, BORN_AT + 61, "friend", 1, 1);
+        match handle_result {
+            Err(StdError::Unauthorized { .. }) => {}
+            _ => panic!("Expected Unauthorized error"),
+        }
+
+        let query_msg = QueryMsg::Caretakers {
+            pet_id: 1,
+            now: BORN_AT + 61,
+        };
+        let caretakers = match from_binary(&query(&deps, query_msg).unwrap()).unwrap() {
+            QueryAnswer::Caretakers { caretakers } => caretakers,
+            _ => panic!("Unexpected query answer"),
+        };
+        assert!(caretakers.is_empty());
+
+        // Re-adding a caretaker replaces the old permission
+        let handle_msg = HandleMsg::AddCaretaker {
+            pet_id: 1,
+            address: HumanAddr("friend".to_string()),
+            expiration: None,
+        };
+        handle(&mut deps, mock_env("owner", &[]), handle_msg).unwrap();
+        let query_msg = QueryMsg::Caretakers {
+            pet_id: 1,
+            now: BORN_AT + 61,
+        };
+        let caretakers = match from_binary(&query(&deps, query_msg).unwrap()).unwrap() {
+            QueryAnswer::Caretakers { caretakers } => caretakers,
+            _ => panic!("Unexpected query answer"),
+        };
+        assert_eq!(
+            caretakers,
+            vec![Caretaker {
+                address: HumanAddr("friend".to_string()),
+                expiration: None,
+            }]
+        );
+        feed_helper(&mut deps, BORN_AT + 61, "friend", 1, 1).unwrap();
+
+        let handle_msg = HandleMsg::RemoveCaretaker {
+            pet_id: 1,
+            address: HumanAddr("friend".to_string()),
+        };
+        handle(&mut deps, mock_env("owner", &[]), handle_msg).unwrap();
+        let handle_result = feed_helper(&mut deps, BORN_AT + 62, "friend", 1, 1);
+        match handle_result {
+            Err(StdError::Unauthorized { .. }) => {}
+            _ => panic!("Expected Unauthorized error"),
+        }
+    }
+
+    #[test]
+    fn test_feed_unknown_pet() {
+        let mut deps = init_helper();
+
+        let handle_result = feed_helper(&mut deps, BORN_AT + 60, "owner", 42, 1);
+        let error = extract_error_msg(handle_result);
+        assert_eq!(error, "Pet 42 does not exist");
+    }
+
+    #[test]
+    fn test_feed_just_in_time() {
+        let mut deps = init_helper();
+        average_metabolism_helper(&mut deps);
+        let fed_at = BORN_AT + FOUR_HOURS;
+
+        let result = feed_helper(&mut deps, fed_at, "owner", 1, 10).unwrap();
+        let burn_msg = FoodHandleMsg::Burn {
+            amount: Uint128(10),
+            padding: None,
+        }
+        .to_cosmos_msg("food_hash".to_string(), HumanAddr("food".to_string()), None)
+        .unwrap();
+        assert_eq!(result.messages, vec![burn_msg]);
+        assert_eq!(
+            result.log,
+            vec![
+                log("action", "feed"),
+                log("pet_id", 1),
+                log("amount", 10),
+                log("stage", "baby"),
+            ]
+        );
+
+        let pet = query_pet_helper(&deps, 1, fed_at + FOUR_HOURS);
+        assert!(pet.is_alive);
+        assert_eq!(pet.full_until, fed_at + FOUR_HOURS);
+    }
+
+    #[test]
+    fn test_feed_one_second_late() {
+        let mut deps = init_helper();
+        average_metabolism_helper(&mut deps);
+
+        let result = feed_helper(&mut deps, BORN_AT + FOUR_HOURS + 1, "owner", 1, 10).unwrap();
+        let transfer_msg = FoodHandleMsg::Transfer {
+            recipient: HumanAddr("owner".to_string()),
+            amount: Uint128(10),
+            padding: None,
+        }
+        .to_cosmos_msg("food_hash".to_string(), HumanAddr("food".to_string()), None)
+        .unwrap();
+        assert_eq!(result.messages, vec![transfer_msg]);
+        assert_eq!(
+            result.log,
+            vec![log("action", "refund"), log("pet_id", 1), log("amount", 10)]
+        );
+
+        let pet = load_pet(&deps.storage, 1).unwrap();
+        assert!(pet.starved);
+        assert_eq!(pet.full_until, BORN_AT + FOUR_HOURS);
+    }
+
+    #[test]
+    fn test_feed_after_death() {
+        let mut deps = init_helper();
+        average_metabolism_helper(&mut deps);
+        let starved_at = BORN_AT + FOUR_HOURS;
+
+        assert!(query_pet_helper(&deps, 1, starved_at).is_alive);
+        assert!(!query_pet_helper(&deps, 1, starved_at + 1).is_alive);
+
+        let result = feed_helper(&mut deps, starved_at + FOUR_HOURS, "owner", 1, 10).unwrap();
+        assert_eq!(result.log[0], log("action", "refund"));
+
+        // Once recorded, death is final even if the clock would say otherwise
+        let result = feed_helper(&mut deps, BORN_AT, "owner", 1, 5).unwrap();
+        assert_eq!(result.log[0], log("action", "refund"));
+        assert!(!query_pet_helper(&deps, 1, BORN_AT).is_alive);
+    }
+
+    #[test]
+    fn test_feed_huge_amount() {
+        let mut deps = init_helper();
+        average_metabolism_helper(&mut deps);
+
+        feed_helper(&mut deps, BORN_AT + 60, "owner", 1, u128::MAX).unwrap();
+        let pet = load_pet(&deps.storage, 1).unwrap();
+        assert_eq!(pet.full_until, BORN_AT + 60 + FOUR_HOURS);
+        assert_eq!(pet.food_eaten, Uint128(u128::MAX));
+    }
+
+    #[test]
+    fn test_feed_proportional_to_amount() {
+        let mut deps = init_helper();
+        average_metabolism_helper(&mut deps);
+
+        let handle_msg = HandleMsg::SetFeedingConfig {
+            starvation_window: None,
+            time_per_food: Some(60),
+            max_satiation: Some(2 * FOUR_HOURS),
+        };
+        handle(&mut deps, mock_env("admin", &[]), handle_msg).unwrap();
+
+        // Each FOOD unit buys a minute
+        let fed_at = BORN_AT + 100;
+        feed_helper(&mut deps, fed_at, "owner", 1, 30).unwrap();
+        let pet = query_pet_helper(&deps, 1, fed_at);
+        assert_eq!(pet.full_until, BORN_AT + FOUR_HOURS + 30 * 60);
+
+        // Feeding is capped by max satiation
+        feed_helper(&mut deps, fed_at, "owner", 1, 1_000).unwrap();
+        let pet = query_pet_helper(&deps, 1, fed_at);
+        assert_eq!(pet.full_until, fed_at + 2 * FOUR_HOURS);
+    }
+
+    #[test]
+    fn test_life_stages() {
+        let mut deps = init_helper();
+        average_metabolism_helper(&mut deps);
+
+        let handle_msg = HandleMsg::SetFeedingConfig {
+            starvation_window: None,
+            time_per_food: None,
+            max_satiation: Some(2 * 24 * 60 * 60),
+        };
+        handle(&mut deps, mock_env("admin", &[]), handle_msg).unwrap();
+
+        // Feeds less than an hour apart count once
+        let result = feed_helper(&mut deps, BORN_AT + 60, "owner", 1, 1).unwrap();
+        assert_eq!(result.log.len(), 3);
+        feed_helper(&mut deps, BORN_AT + 120, "owner", 1, 1).unwrap();
+        assert_eq!(load_pet(&deps.storage, 1).unwrap().streak, 1);
+
+        let result = feed_helper(&mut deps, BORN_AT + STREAK_INTERVAL + 60, "owner", 1, 1).unwrap();
+        assert_eq!(result.log[3], log("stage", "baby"));
+        let pet = load_pet(&deps.storage, 1).unwrap();
+        assert_eq!(pet.streak, 2);
+        assert_eq!(pet.stage, LifeStage::Baby);
+
+        // Old enough, but not fed consistently enough to grow up
+        let two_days = 2 * 24 * 60 * 60;
+        feed_helper(
+            &mut deps,
+            BORN_AT + 2 * STREAK_INTERVAL + 60,
+            "owner",
+            1,
+            12,
+        )
+        .unwrap();
+        let time = BORN_AT + two_days;
+        let result = feed_helper(&mut deps, time, "owner", 1, 1).unwrap();
+        assert_eq!(result.log.len(), 3);
+        assert_eq!(load_pet(&deps.storage, 1).unwrap().streak, 4);
+
+        let result = feed_helper(&mut deps, time + STREAK_INTERVAL, "owner", 1, 1).unwrap();
+        assert_eq!(result.log[3], log("stage", "child"));
+
+        // Stage is reported at the time of the query, and stops growing at death,
+        // a week old pet with this streak would be an adult if it were still alive
+        let mut pet = load_pet(&deps.storage, 1).unwrap();
+        pet.streak = 15;
+        let week = 7 * 24 * 60 * 60;
+        assert!(pet.died_at(BORN_AT + week).is_some());
+        assert_eq!(pet.stage_at(BORN_AT + week), LifeStage::Child);
+        assert_eq!(LifeStage::from_progress(week, 15), LifeStage::Adult);
+    }
+
+    #[test]
+    fn test_feed_other_foods() {
+        let mut deps = init_helper();
+        average_metabolism_helper(&mut deps);
+
+        let handle_msg = HandleMsg::SetFood {
+            contract: HumanAddr("snack".to_string()),
+            code_hash: "snack_hash".to_string(),
+            nutrition: 60,
+            burn: false,
+        };
+        let handle_result = handle(&mut deps, mock_env("owner", &[]), handle_msg.clone());
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("This is an admin command"));
+
+        let env = mock_env("admin", &[]);
+        let result = handle(&mut deps, env.clone(), handle_msg).unwrap();
+        let register_msg = FoodHandleMsg::RegisterReceive {
+            code_hash: env.contract_code_hash,
+            padding: None,
+        }
+        .to_cosmos_msg(
+            "snack_hash".to_string(),
+            HumanAddr("snack".to_string()),
+            None,
+        )
+        .unwrap();
+        assert_eq!(result.messages, vec![register_msg]);
+
+        // Snacks are kept by the Pet instead of being burned
+        let mut env = mock_env("snack", &[]);
+        env.block.time = BORN_AT;
+        let feed_msg = HandleMsg::Receive {
+            sender: HumanAddr("owner".to_string()),
+            from: HumanAddr("owner".to_string()),
+            amount: Uint128(10),
+            msg: Some(to_binary(&ReceiveMsg::Feed { pet_id: 1 }).unwrap()),
+        };
+        let result = handle(&mut deps, env.clone(), feed_msg.clone()).unwrap();
+        assert!(result.messages.is_empty());
+        let pet = load_pet(&deps.storage, 1).unwrap();
+        assert_eq!(pet.full_until, BORN_AT + FOUR_HOURS);
+
+        // Admin can move the kept snacks out of the Pet's balance
+        let withdraw_helper =
+            |deps: &mut Extern<MockStorage, MockApi, MockQuerier>, sender, amount| {
+                let handle_msg = HandleMsg::WithdrawHeldFood {
+                    contract: HumanAddr("snack".to_string()),
+                    code_hash: "snack_hash".to_string(),
+                    recipient: HumanAddr("treasury".to_string()),
+                    amount,
+                };
+                handle(deps, mock_env(sender, &[]), handle_msg)
+            };
+        let handle_result = withdraw_helper(&mut deps, "owner", None);
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("This is an admin command"));
+        let handle_result = withdraw_helper(&mut deps, "admin", Some(Uint128(11)));
+        let error = extract_error_msg(handle_result);
+        assert_eq!(error, "Only 10 of this food is held");
+
+        let result = withdraw_helper(&mut deps, "admin", Some(Uint128(4))).unwrap();
+        let transfer_msg = FoodHandleMsg::Transfer {
+            recipient: HumanAddr("treasury".to_string()),
+            amount: Uint128(4),
+            padding: None,
+        }
+        .to_cosmos_msg(
+            "snack_hash".to_string(),
+            HumanAddr("snack".to_string()),
+            None,
+        )
+        .unwrap();
+        assert_eq!(result.messages, vec![transfer_msg]);
+        let result = withdraw_helper(&mut deps, "admin", None).unwrap();
+        let answer: HandleAnswer = from_binary(&result.data.unwrap()).unwrap();
+        assert_eq!(
+            answer,
+            HandleAnswer::WithdrawHeldFood { amount: Uint128(6) }
+        );
+        let held = read_held_food(&deps.storage, &HumanAddr("snack".to_string())).unwrap();
+        assert_eq!(held, Uint128::zero());
+
+        let handle_msg = HandleMsg::SetFood {
+            contract: HumanAddr("snack".to_string()),
+            code_hash: "snack_hash".to_string(),
+            nutrition: 60,
+            burn: true,
+        };
+        handle(&mut deps, mock_env("admin", &[]), handle_msg).unwrap();
+
+        let query_result = query(&deps, QueryMsg::Foods {}).unwrap();
+        let foods = match from_binary(&query_result).unwrap() {
+            QueryAnswer::Foods { foods } => foods,
+            _ => panic!("Unexpected query answer"),
+        };
+        assert_eq!(foods.len(), 2);
+        assert_eq!(foods[0].contract, HumanAddr("food".to_string()));
+        assert_eq!(foods[0].nutrition, FOUR_HOURS);
+        assert_eq!(foods[1].contract, HumanAddr("snack".to_string()));
+        assert!(foods[1].burn);
+
+        let handle_msg = HandleMsg::RemoveFood {
+            contract: HumanAddr("snack".to_string()),
+        };
+        handle(&mut deps, mock_env("admin", &[]), handle_msg).unwrap();
+        let handle_result = handle(&mut deps, env, feed_msg);
+        let error = extract_error_msg(handle_result);
+        assert_eq!(error, "Only FOOD tokens are accepted");
+    }
+
+    #[test]
+    fn test_revive() {
+        let mut deps = init_helper();
+        average_metabolism_helper(&mut deps);
+        let dead_at = BORN_AT + FOUR_HOURS + 1;
+        let revive_msg = ReceiveMsg::Revive { pet_id: 1 };
+
+        let handle_result = receive_helper(&mut deps, BORN_AT, "owner", 3, revive_msg.clone());
+        let error = extract_error_msg(handle_result);
+        assert_eq!(error, "Pet 1 is alive");
+
+        let handle_result = receive_helper(&mut deps, dead_at, "owner", 2, revive_msg.clone());
+        let error = extract_error_msg(handle_result);
+        assert_eq!(error, "Reviving a pet costs 3 FOOD");
+
+        let result = receive_helper(&mut deps, dead_at, "owner", 3, revive_msg.clone()).unwrap();
+        let burn_msg = FoodHandleMsg::Burn {
+            amount: Uint128(3),
+            padding: None,
+        }
+        .to_cosmos_msg("food_hash".to_string(), HumanAddr("food".to_string()), None)
+        .unwrap();
+        assert_eq!(result.messages, vec![burn_msg]);
+        assert_eq!(
+            result.log,
+            vec![
+                log("action", "revive"),
+                log("pet_id", 1),
+                log("amount", 3),
+                log("deaths", 1),
+            ]
+        );
+
+        let query_msg = QueryMsg::Status {
+            pet_id: 1,
+            now: dead_at,
+        };
+        let status = match from_binary(&query(&deps, query_msg).unwrap()).unwrap() {
+            QueryAnswer::Status { status } => status,
+            _ => panic!("Unexpected query answer"),
+        };
+        assert!(status.is_alive);
+        assert_eq!(status.full_until, dead_at + FOUR_HOURS);
+        assert_eq!(status.died_at, None);
+        assert_eq!(status.deaths, 1);
+
+        // Escrow which only covered part of the lapsed time is burned along with the revival
+        let deposit_msg = ReceiveMsg::DepositEscrow { pet_id: 1 };
+        receive_helper(&mut deps, dead_at, "owner", 1, deposit_msg).unwrap();
+        let revived_at = dead_at + 3 * FOUR_HOURS;
+        let result = receive_helper(&mut deps, revived_at, "owner", 3, revive_msg.clone()).unwrap();
+        let burn_msgs: Vec<CosmosMsg> = [1, 3]
+            .iter()
+            .map(|amount| {
+                FoodHandleMsg::Burn {
+                    amount: Uint128(*amount),
+                    padding: None,
+                }
+                .to_cosmos_msg("food_hash".to_string(), HumanAddr("food".to_string()), None)
+                .unwrap()
+            })
+            .collect();
+        assert_eq!(result.messages, burn_msgs);
+        let pet = load_pet(&deps.storage, 1).unwrap();
+        assert_eq!(pet.escrow, Uint128::zero());
+        assert_eq!(pet.deaths, 2);
+
+        // Hardcore mode
+        let handle_msg = HandleMsg::SetRevivalConfig {
+            enabled: Some(false),
+            multiplier: None,
+        };
+        handle(&mut deps, mock_env("admin", &[]), handle_msg).unwrap();
+        let dead_at = dead_at + FOUR_HOURS + 1;
+        let handle_result = receive_helper(&mut deps, dead_at, "owner", 3, revive_msg);
+        let error = extract_error_msg(handle_result);
+        assert_eq!(error, "Revival is disabled");
+    }
+
+    #[test]
+    fn test_settle() {
+        let mut deps = init_helper();
+        average_metabolism_helper(&mut deps);
+        let dead_at = BORN_AT + FOUR_HOURS + 1;
+
+        let settle_helper = |deps: &mut Extern<MockStorage, MockApi, MockQuerier>, time, pet_id| {
+            let mut env = mock_env("keeper", &[]);
+            env.block.time = time;
+            handle(deps, env, HandleMsg::Settle { pet_id })
+        };
+
+        let handle_result = settle_helper(&mut deps, dead_at - 1, 1);
+        let error = extract_error_msg(handle_result);
+        assert_eq!(error, "Pet 1 has nothing to settle");
+
+        // Without a pool, settling is done for free
+        let result = settle_helper(&mut deps, dead_at, 1).unwrap();
+        assert!(result.messages.is_empty());
+        assert_eq!(
+            result.log,
+            vec![
+                log("action", "settle"),
+                log("pet_id", 1),
+                log("died_at", BORN_AT + FOUR_HOURS),
+                log("keeper", "keeper"),
+                log("bounty", 0),
+            ]
+        );
+        let pet = load_pet(&deps.storage, 1).unwrap();
+        assert!(pet.starved);
+        assert_eq!(pet.died_at, Some(BORN_AT + FOUR_HOURS));
+
+        let handle_result = settle_helper(&mut deps, dead_at, 1);
+        let error = extract_error_msg(handle_result);
+        assert_eq!(error, "Pet 1 has nothing to settle");
+
+        let handle_msg = HandleMsg::SetSettleBounty { bounty: Uint128(2) };
+        handle(&mut deps, mock_env("admin", &[]), handle_msg).unwrap();
+        receive_helper(&mut deps, BORN_AT, "sponsor", 3, ReceiveMsg::FundPool {}).unwrap();
+
+        // Settling an escrow debit of a living pet only burns the FOOD
+        let pet_id = create_pet_helper(&mut deps, "owner", "Funded");
+        let deposit_msg = ReceiveMsg::DepositEscrow { pet_id };
+        receive_helper(&mut deps, BORN_AT, "owner", 5, deposit_msg).unwrap();
+        let result = settle_helper(&mut deps, BORN_AT + 2 * FOUR_HOURS, pet_id).unwrap();
+        assert!(query_pet_helper(&deps, pet_id, BORN_AT + 2 * FOUR_HOURS).is_alive);
+        assert_eq!(result.messages.len(), 1);
+        assert!(result.log.contains(&log("bounty", 0)));
+        assert_eq!(config_read(&deps.storage).load().unwrap().pool, Uint128(3));
+
+        // Bounty is limited by what's left in the pool
+        for expected in &[2, 1] {
+            let pet_id = create_pet_helper(&mut deps, "owner", "Doomed");
+            let result = settle_helper(&mut deps, BORN_AT + 2 * FOUR_HOURS, pet_id).unwrap();
+            let transfer_msg = FoodHandleMsg::Transfer {
+                recipient: HumanAddr("keeper".to_string()),
+                amount: Uint128(*expected),
+                padding: None,
+            }
+            .to_cosmos_msg("food_hash".to_string(), HumanAddr("food".to_string()), None)
+            .unwrap();
+            assert_eq!(result.messages, vec![transfer_msg]);
+        }
+        assert_eq!(
+            config_read(&deps.storage).load().unwrap().pool,
+            Uint128::zero()
+        );
+    }
+
+    #[test]
+    fn test_games() {
+        let mut deps = init_helper();
+        receive_helper(&mut deps, BORN_AT, "sponsor", 10, ReceiveMsg::FundPool {}).unwrap();
+
+        let wager_helper =
+            |deps: &mut Extern<MockStorage, MockApi, MockQuerier>, time, amount, game| {
+                let receive_msg = ReceiveMsg::Play { pet_id: 1, game };
+                receive_helper(deps, time, "owner", amount, receive_msg)
+            };
+        let heads = Game::CoinFlip { guess: Coin::Heads };
+        let handle_result = wager_helper(&mut deps, BORN_AT + 1, 11, heads.clone());
+        let error = extract_error_msg(handle_result);
+        assert_eq!(error, "Pool can't cover a win of 22 FOOD");
+
+        let handle_result = wager_helper(&mut deps, BORN_AT + 1, 1, Game::HideAndSeek { spot: 4 });
+        let error = extract_error_msg(handle_result);
+        assert_eq!(error, "Pet can only hide in spots 0 to 3");
+
+        // Here the coin lands on heads
+        let tails = Game::CoinFlip { guess: Coin::Tails };
+        let result = wager_helper(&mut deps, BORN_AT + 1, 2, tails).unwrap();
+        let answer: HandleAnswer = from_binary(&result.data.unwrap()).unwrap();
+        assert_eq!(
+            answer,
+            HandleAnswer::Game {
+                won: false,
+                payout: Uint128(0)
+            }
+        );
+        assert!(result.log.contains(&log("game", "coin_flip")));
+        assert!(result.log.contains(&log("outcome", "heads")));
+        assert!(result.messages.is_empty());
+        let config_state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(config_state.pool, Uint128(12));
+
+        // And on heads again a few seconds later
+        let result = wager_helper(&mut deps, BORN_AT + 5, 2, heads).unwrap();
+        let answer: HandleAnswer = from_binary(&result.data.unwrap()).unwrap();
+        assert_eq!(
+            answer,
+            HandleAnswer::Game {
+                won: true,
+                payout: Uint128(4)
+            }
+        );
+        assert!(result.log.contains(&log("outcome", "heads")));
+        let transfer_msg = FoodHandleMsg::Transfer {
+            recipient: HumanAddr("owner".to_string()),
+            amount: Uint128(4),
+            padding: None,
+        }
+        .to_cosmos_msg("food_hash".to_string(), HumanAddr("food".to_string()), None)
+        .unwrap();
+        assert_eq!(result.messages, vec![transfer_msg]);
+        let config_state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(config_state.pool, Uint128(10));
+
+        // Games can be played for fun too
+        let play_helper = |deps: &mut Extern<MockStorage, MockApi, MockQuerier>, time| {
+            let handle_msg = HandleMsg::Play {
+                pet_id: 1,
+                game: Some(Game::HideAndSeek { spot: 2 }),
+            };
+            let mut env = mock_env("owner", &[]);
+            env.block.time = time;
+            handle(deps, env, handle_msg)
+        };
+        let result = play_helper(&mut deps, BORN_AT + 6).unwrap();
+        assert!(result.log.contains(&log("game", "hide_and_seek")));
+        assert!(result.log.contains(&log("outcome", 2)));
+        assert!(result.log.contains(&log("won", true)));
+        assert!(result.log.contains(&log("payout", 0)));
+        assert!(result.messages.is_empty());
+
+        // Every roll bumps the nonce, so a free roll doesn't reveal the next one in the same block
+        let mut config_state = config_read(&deps.storage).load().unwrap();
+        let mut env = mock_env("owner", &[]);
+        env.block.time = BORN_AT + 6;
+        let owner = HumanAddr("owner".to_string());
+        let first = pet_prng(&mut config_state, &env, &owner, 1).rand_bytes();
+        let second = pet_prng(&mut config_state, &env, &owner, 1).rand_bytes();
+        assert_ne!(first, second);
+
+        // They still tire the pet out
+        let pet = load_pet(&deps.storage, 1).unwrap();
+        assert_eq!(
+            pet.energy.value_at(BORN_AT + 6, DEFAULT_ENERGY_DECAY),
+            MAX_STAT - 3 * PLAY_ENERGY
+        );
+
+        // Hibernating pets can't play
+        let hibernate_msg = ReceiveMsg::Hibernate {
+            pet_id: 1,
+            until: BORN_AT + FOUR_HOURS,
+        };
+        receive_helper(&mut deps, BORN_AT + 10, "owner", 1, hibernate_msg).unwrap();
+        let handle_result = play_helper(&mut deps, BORN_AT + 20);
+        let error = extract_error_msg(handle_result);
+        assert_eq!(error, "Pet 1 is hibernating");
+    }
+
+    #[test]
+    fn test_illness() {
+        let mut deps = init_helper();
+        create_pet_helper(&mut deps, "owner2", "Fido");
+        average_metabolism_helper(&mut deps);
+
+        let handle_msg = HandleMsg::SetMedicine {
+            contract: HumanAddr("medicine".to_string()),
+            code_hash: "medicine_hash".to_string(),
+        };
+        handle(&mut deps, mock_env("admin", &[]), handle_msg).unwrap();
+
+        // Neglected pets fall ill sooner or later, here it's always fed a minute before starving
+        let mut time = BORN_AT + FOUR_HOURS - 60;
+        let ill_since = loop {
+            let result = feed_helper(&mut deps, time, "owner", 1, 2).unwrap();
+            if result.log.contains(&log("illness", "fell_ill")) {
+                break time;
+            }
+            assert!(time < BORN_AT + 50 * FOUR_HOURS, "Pet never fell ill");
+            time += FOUR_HOURS - 60;
+        };
+        let pet = load_pet(&deps.storage, 1).unwrap();
+        assert_eq!(pet.ill_since, Some(ill_since));
+        // Satiation runs out twice as fast
+        assert_eq!(pet.deadline(ill_since), ill_since + FOUR_HOURS / 2);
+
+        let treat_msg = ReceiveMsg::Treat { pet_id: 1 };
+        let handle_result = receive_helper(&mut deps, ill_since, "owner", 1, treat_msg.clone());
+        let error = extract_error_msg(handle_result);
+        assert_eq!(error, "Pets can only be treated with medicine");
+
+        let treat_helper = |deps: &mut Extern<MockStorage, MockApi, MockQuerier>, time, amount| {
+            let mut env = mock_env("medicine", &[]);
+            env.block.time = time;
+            let handle_msg = HandleMsg::Receive {
+                sender: HumanAddr("owner".to_string()),
+                from: HumanAddr("owner".to_string()),
+                amount: Uint128(amount),
+                msg: Some(to_binary(&treat_msg).unwrap()),
+            };
+            handle(deps, env, handle_msg)
+        };
+        let treated_at = ill_since + 60 * 60;
+        let handle_result = treat_helper(&mut deps, treated_at, 2);
+        let error = extract_error_msg(handle_result);
+        assert_eq!(error, "Treating a pet takes 1 medicine");
+
+        let result = treat_helper(&mut deps, treated_at, 1).unwrap();
+        let burn_msg = FoodHandleMsg::Burn {
+            amount: Uint128(1),
+            padding: None,
+        }
+        .to_cosmos_msg(
+            "medicine_hash".to_string(),
+            HumanAddr("medicine".to_string()),
+            None,
+        )
+        .unwrap();
+        assert_eq!(result.messages, vec![burn_msg]);
+        let pet = load_pet(&deps.storage, 1).unwrap();
+        assert_eq!(pet.ill_since, None);
+        assert_eq!(pet.full_until, ill_since + FOUR_HOURS / 2);
+
+        let handle_result = treat_helper(&mut deps, treated_at, 1);
+        let error = extract_error_msg(handle_result);
+        assert_eq!(error, "Pet 1 is not ill");
+
+        // Hibernating pets can't be treated until they wake up
+        create_pet_helper(&mut deps, "owner3", "Kit");
+        let hibernate_msg = ReceiveMsg::Hibernate {
+            pet_id: 3,
+            until: BORN_AT + FOUR_HOURS,
+        };
+        receive_helper(&mut deps, BORN_AT, "owner3", 1, hibernate_msg).unwrap();
+        let mut pet = load_pet(&deps.storage, 3).unwrap();
+        pet.fall_ill(BORN_AT);
+        save_pet(&mut deps.storage, &pet).unwrap();
+        let mut env = mock_env("medicine", &[]);
+        env.block.time = BORN_AT + 60 * 60;
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("owner3".to_string()),
+            from: HumanAddr("owner3".to_string()),
+            amount: Uint128(1),
+            msg: Some(to_binary(&ReceiveMsg::Treat { pet_id: 3 }).unwrap()),
+        };
+        let handle_result = handle(&mut deps, env, handle_msg);
+        let error = extract_error_msg(handle_result);
+        assert_eq!(error, "Pet 3 is hibernating");
+        let pet = load_pet(&deps.storage, 3).unwrap();
+        assert!(pet.is_hibernating(BORN_AT + 60 * 60));
+        assert_eq!(pet.ill_since, Some(BORN_AT));
+
+        // Pets which starve while ill die of the illness
+        let mut pet = load_pet(&deps.storage, 2).unwrap();
+        pet.fall_ill(BORN_AT);
+        save_pet(&mut deps.storage, &pet).unwrap();
+        let mut env = mock_env("keeper", &[]);
+        env.block.time = BORN_AT + FOUR_HOURS / 2 + 1;
+        handle(&mut deps, env, HandleMsg::Settle { pet_id: 2 }).unwrap();
+        let pet = load_pet(&deps.storage, 2).unwrap();
+        assert_eq!(pet.died_at, Some(BORN_AT + FOUR_HOURS / 2));
+
+        let health_helper = |deps: &Extern<MockStorage, MockApi, MockQuerier>, pet_id| {
+            let query_msg = QueryMsg::Health {
+                pet_id,
+                page: None,
+                page_size: 10,
+            };
+            match from_binary(&query(deps, query_msg).unwrap()).unwrap() {
+                QueryAnswer::Health { illnesses, .. } => illnesses,
+                _ => panic!("Unexpected query answer"),
+            }
+        };
+        let treated = Illness {
+            since: ill_since,
+            until: treated_at,
+            outcome: IllnessOutcome::Treated,
+        };
+        assert_eq!(health_helper(&deps, 1), vec![treated]);
+        let died = Illness {
+            since: BORN_AT,
+            until: BORN_AT + FOUR_HOURS / 2,
+            outcome: IllnessOutcome::Died,
+        };
+        assert_eq!(health_helper(&deps, 2), vec![died]);
+        let memorials = get_memorials(&deps.storage, 0, 1).unwrap();
+        assert_eq!(memorials[0].cause, DeathCause::Illness);
+    }
+
+    #[test]
+    fn test_escrow() {
+        let mut deps = init_helper();
+        average_metabolism_helper(&mut deps);
+
+        let deposit_msg = ReceiveMsg::DepositEscrow { pet_id: 1 };
+        let handle_result = receive_helper(&mut deps, BORN_AT, "stranger", 2, deposit_msg.clone());
+        match handle_result {
+            Err(StdError::Unauthorized { .. }) => {}
+            _ => panic!("Expected Unauthorized error"),
+        }
+        receive_helper(&mut deps, BORN_AT, "owner", 2, deposit_msg).unwrap();
+
+        let escrow_helper = |deps: &Extern<MockStorage, MockApi, MockQuerier>, now| {
+            let query_msg = QueryMsg::Escrow { pet_id: 1, now };
+            match from_binary(&query(deps, query_msg).unwrap()).unwrap() {
+                QueryAnswer::Escrow { amount } => amount.u128(),
+                _ => panic!("Unexpected query answer"),
+            }
+        };
+        let expired_at = BORN_AT + FOUR_HOURS + 1;
+        assert_eq!(escrow_helper(&deps, expired_at - 1), 2);
+        assert_eq!(escrow_helper(&deps, expired_at), 1);
+        assert!(query_pet_helper(&deps, 1, expired_at).is_alive);
+
+        // Keepers apply the debit and burn the FOOD it used
+        let mut env = mock_env("keeper", &[]);
+        env.block.time = expired_at;
+        let result = handle(&mut deps, env, HandleMsg::Settle { pet_id: 1 }).unwrap();
+        let burn_msg = FoodHandleMsg::Burn {
+            amount: Uint128(1),
+            padding: None,
+        }
+        .to_cosmos_msg("food_hash".to_string(), HumanAddr("food".to_string()), None)
+        .unwrap();
+        assert_eq!(result.messages, vec![burn_msg]);
+        assert!(result.log.contains(&log("escrow_used", 1)));
+        let pet = load_pet(&deps.storage, 1).unwrap();
+        assert_eq!(pet.full_until, BORN_AT + 2 * FOUR_HOURS);
+        assert_eq!(pet.food_eaten, Uint128(1));
+
+        let withdraw_helper = |deps: &mut Extern<MockStorage, MockApi, MockQuerier>, amount| {
+            let handle_msg = HandleMsg::WithdrawEscrow {
+                pet_id: 1,
+                amount,
+                padding: None,
+            };
+            handle(deps, mock_env("owner", &[]), handle_msg)
+        };
+        let handle_result = withdraw_helper(&mut deps, Some(Uint128(2)));
+        let error = extract_error_msg(handle_result);
+        assert_eq!(error, "Escrow only has 1 FOOD");
+
+        let result = withdraw_helper(&mut deps, None).unwrap();
+        let transfer_msg = FoodHandleMsg::Transfer {
+            recipient: HumanAddr("owner".to_string()),
+            amount: Uint128(1),
+            padding: None,
+        }
+        .to_cosmos_msg("food_hash".to_string(), HumanAddr("food".to_string()), None)
+        .unwrap();
+        assert_eq!(result.messages, vec![transfer_msg]);
+        assert_eq!(escrow_helper(&deps, BORN_AT + 3 * FOUR_HOURS), 0);
+        assert!(!query_pet_helper(&deps, 1, BORN_AT + 2 * FOUR_HOURS + 1).is_alive);
+
+        // Caretakers can feed the pet, but not fund its escrow
+        let handle_msg = HandleMsg::AddCaretaker {
+            pet_id: 1,
+            address: HumanAddr("caretaker".to_string()),
+            expiration: None,
+        };
+        handle(&mut deps, mock_env("owner", &[]), handle_msg).unwrap();
+        let deposit_msg = ReceiveMsg::DepositEscrow { pet_id: 1 };
+        let now = BORN_AT + FOUR_HOURS;
+        let handle_result = receive_helper(&mut deps, now, "caretaker", 3, deposit_msg.clone());
+        match handle_result {
+            Err(StdError::Unauthorized { .. }) => {}
+            _ => panic!("Expected Unauthorized error"),
+        }
+
+        // Escrow left when the pet changes hands is returned to the previous owner
+        receive_helper(&mut deps, now, "owner", 3, deposit_msg).unwrap();
+        let handle_msg = HandleMsg::TransferNft {
+            recipient: HumanAddr("buyer".to_string()),
+            token_id: "1".to_string(),
+            memo: None,
+            padding: None,
+        };
+        let mut env = mock_env("owner", &[]);
+        env.block.time = now;
+        let result = handle(&mut deps, env, handle_msg).unwrap();
+        let transfer_msg = FoodHandleMsg::Transfer {
+            recipient: HumanAddr("owner".to_string()),
+            amount: Uint128(3),
+            padding: None,
+        }
+        .to_cosmos_msg("food_hash".to_string(), HumanAddr("food".to_string()), None)
+        .unwrap();
+        assert_eq!(result.messages, vec![transfer_msg]);
+        assert_eq!(load_pet(&deps.storage, 1).unwrap().escrow, Uint128::zero());
+    }
+
+    #[test]
+    fn test_graveyard() {
+        let mut deps = init_helper();
+        create_pet_helper(&mut deps, "owner2", "Fido");
+        average_metabolism_helper(&mut deps);
+        let dead_at = BORN_AT + FOUR_HOURS + 1;
+
+        let mut env = mock_env("keeper", &[]);
+        env.block.time = dead_at;
+        handle(&mut deps, env.clone(), HandleMsg::Settle { pet_id: 1 }).unwrap();
+        // Food sent to a dead pet records its death as well
+        feed_helper(&mut deps, dead_at, "owner2", 2, 1).unwrap();
+        feed_helper(&mut deps, dead_at + 1, "owner2", 2, 1).unwrap();
+
+        let graveyard_helper = |deps: &Extern<MockStorage, MockApi, MockQuerier>,
+                                owner: Option<&str>| {
+            let query_msg = QueryMsg::Graveyard {
+                owner: owner.map(|owner| HumanAddr(owner.to_string())),
+                page: None,
+                page_size: 10,
+            };
+            match from_binary(&query(deps, query_msg).unwrap()).unwrap() {
+                QueryAnswer::Graveyard { memorials } => memorials,
+                _ => panic!("Unexpected query answer"),
+            }
+        };
+        let memorials = graveyard_helper(&deps, None);
+        let pet_ids: Vec<u64> = memorials.iter().map(|memorial| memorial.pet_id).collect();
+        assert_eq!(pet_ids, vec![2, 1]);
+
+        let memorials = graveyard_helper(&deps, Some("owner"));
+        assert_eq!(
+            memorials,
+            vec![Memorial {
+                pet_id: 1,
+                name: "Rex".to_string(),
+                owner: HumanAddr("owner".to_string()),
+                born_at: BORN_AT,
+                died_at: BORN_AT + FOUR_HOURS,
+                lifespan: FOUR_HOURS,
+                cause: DeathCause::Starvation,
+                stage: LifeStage::Baby,
+                happiness: MAX_STAT - 4 * DEFAULT_HAPPINESS_DECAY,
+                cleanliness: MAX_STAT - 4 * DEFAULT_CLEANLINESS_DECAY,
+                energy: MAX_STAT - 4 * DEFAULT_ENERGY_DECAY,
+                food_eaten: Uint128::zero(),
+                feed_count: 0,
+                generation: 0,
+            }]
+        );
+
+        // Memorials stay after the pet changes hands
+        let handle_msg = HandleMsg::TransferNft {
+            recipient: HumanAddr("buyer".to_string()),
+            token_id: "1".to_string(),
+            memo: None,
+            padding: None,
+        };
+        handle(&mut deps, mock_env("owner", &[]), handle_msg).unwrap();
+        assert_eq!(graveyard_helper(&deps, Some("owner")).len(), 1);
+        assert!(graveyard_helper(&deps, Some("buyer")).is_empty());
+    }
+
+    #[test]
+    fn test_hibernate() {
+        let mut deps = init_helper();
+        average_metabolism_helper(&mut deps);
+        let hour = 60 * 60;
+        let since = BORN_AT + hour;
+        let until = since + 24 * hour;
+        let hibernate_msg = ReceiveMsg::Hibernate { pet_id: 1, until };
+
+        let handle_result = receive_helper(&mut deps, since, "owner", 2, hibernate_msg.clone());
+        let error = extract_error_msg(handle_result);
+        assert_eq!(error, "Hibernation costs 1 FOOD");
+
+        let too_long = ReceiveMsg::Hibernate {
+            pet_id: 1,
+            until: since + DEFAULT_MAX_HIBERNATION + 1,
+        };
+        let handle_result = receive_helper(&mut deps, since, "owner", 1, too_long);
+        let error = extract_error_msg(handle_result);
+        assert!(error.starts_with("Hibernation must end in the future"));
+
+        let result = receive_helper(&mut deps, since, "owner", 1, hibernate_msg).unwrap();
+        let burn_msg = FoodHandleMsg::Burn {
+            amount: Uint128(1),
+            padding: None,
+        }
+        .to_cosmos_msg("food_hash".to_string(), HumanAddr("food".to_string()), None)
+        .unwrap();
+        assert_eq!(result.messages, vec![burn_msg]);
+
+        // Stats only decay during the hour before the pet fell asleep, also once it's woken
+        let wellbeing_helper = |deps: &Extern<MockStorage, MockApi, MockQuerier>, now| {
+            let query_msg = QueryMsg::Status { pet_id: 1, now };
+            match from_binary(&query(deps, query_msg).unwrap()).unwrap() {
+                QueryAnswer::Status { status } => status.wellbeing,
+                _ => panic!("Unexpected query answer"),
+            }
+        };
+        let rested = wellbeing_helper(&deps, since + 10 * hour);
+        assert_eq!(rested.happiness, MAX_STAT - DEFAULT_HAPPINESS_DECAY);
+        assert_eq!(rested.cleanliness, MAX_STAT - DEFAULT_CLEANLINESS_DECAY);
+        assert_eq!(rested.energy, MAX_STAT - DEFAULT_ENERGY_DECAY);
+
+        // Countdown is frozen, and the pet can't be fed
+        let pet = query_pet_helper(&deps, 1, since + 10 * hour);
+        assert!(pet.is_alive);
+        assert_eq!(pet.full_until, BORN_AT + FOUR_HOURS + 10 * hour);
+        let handle_result = feed_helper(&mut deps, since + 10 * hour, "owner", 1, 1);
+        let error = extract_error_msg(handle_result);
+        assert_eq!(error, "Pet 1 is hibernating");
+
+        // Waking up early keeps the three hours the pet had left
+        let mut env = mock_env("owner", &[]);
+        env.block.time = since + 10 * hour;
+        handle(&mut deps, env.clone(), HandleMsg::Wake { pet_id: 1 }).unwrap();
+        let pet = load_pet(&deps.storage, 1).unwrap();
+        assert_eq!(pet.full_until, since + 10 * hour + 3 * hour);
+        assert_eq!(pet.hibernation, None);
+
+        assert_eq!(wellbeing_helper(&deps, since + 10 * hour), rested);
+
+        let handle_result = handle(&mut deps, env, HandleMsg::Wake { pet_id: 1 });
+        let error = extract_error_msg(handle_result);
+        assert_eq!(error, "Pet 1 is not hibernating");
+
+        // Pet has to be fed before it can hibernate again
+        let since = since + 10 * hour;
+        let until = since + hour;
+        let hibernate_msg = ReceiveMsg::Hibernate { pet_id: 1, until };
+        let handle_result = receive_helper(&mut deps, since, "owner", 1, hibernate_msg.clone());
+        let error = extract_error_msg(handle_result);
+        assert_eq!(error, "Pet 1 has to be fed before hibernating again");
+        feed_helper(&mut deps, since, "owner", 1, 1).unwrap();
+        let left = load_pet(&deps.storage, 1).unwrap().full_until - since;
+
+        // Countdown resumes on its own once hibernation is over
+        receive_helper(&mut deps, since, "owner", 1, hibernate_msg).unwrap();
+        assert!(query_pet_helper(&deps, 1, until + left).is_alive);
+        assert!(!query_pet_helper(&deps, 1, until + left + 1).is_alive);
+        feed_helper(&mut deps, until + left, "owner", 1, 2).unwrap();
+        let pet = query_pet_helper(&deps, 1, until + left);
+        assert_eq!(pet.full_until, until + left + FOUR_HOURS);
+    }
+
+    #[test]
+    fn test_breed() {
+        let mut deps = init_helper();
+        create_pet_helper(&mut deps, "owner", "Bella");
+        create_pet_helper(&mut deps, "other", "Max");
+        let breed_msg = |pet_id, partner_id| ReceiveMsg::Breed {
+            pet_id,
+            partner_id,
+            name: "Puppy".to_string(),
+        };
+
+        let handle_result = receive_helper(&mut deps, BORN_AT, "owner", 10, breed_msg(1, 2));
+        let error = extract_error_msg(handle_result);
+        assert_eq!(error, "Pet 1 is too young to breed");
+
+        // Grow pets 1, 2 and 3 into adults
+        let adult = LifeStage::Adult;
+        let mut pets: Vec<State> = (1..=3)
+            .map(|pet_id| load_pet(&deps.storage, pet_id).unwrap())
+            .collect();
+        for pet in pets.iter_mut() {
+            pet.stage = adult;
+            save_pet(&mut deps.storage, pet).unwrap();
+        }
+
+        let handle_result = receive_helper(&mut deps, BORN_AT, "owner", 5, breed_msg(1, 2));
+        let error = extract_error_msg(handle_result);
+        assert_eq!(error, "Breeding costs 10 FOOD");
+
+        let result = receive_helper(&mut deps, BORN_AT, "owner", 10, breed_msg(1, 2)).unwrap();
+        assert_eq!(
+            result.log,
+            vec![
+                log("action", "breed"),
+                log("pet_id", 4),
+                log("parents", "1,2")
+            ]
+        );
+
+        let child = load_pet(&deps.storage, 4).unwrap();
+        assert_eq!(child.owner, HumanAddr("owner".to_string()));
+        assert_eq!(child.stage, LifeStage::Egg);
+        assert_eq!(child.dna.len(), 32);
+        assert_ne!(child.dna, pets[0].dna);
+
+        // Other owner's pet needs consent, which only counts once
+        let handle_result = receive_helper(&mut deps, BORN_AT, "owner", 10, breed_msg(1, 3));
+        match handle_result {
+            Err(StdError::Unauthorized { .. }) => {}
+            _ => panic!("Expected Unauthorized error"),
+        }
+        let handle_msg = HandleMsg::ApproveBreeding {
+            pet_id: 3,
+            partner_id: Some(1),
+        };
+        let result = handle(&mut deps, mock_env("other", &[]), handle_msg).unwrap();
+        assert_eq!(
+            result.log,
+            vec![
+                log("action", "approve_breeding"),
+                log("pet_id", 3),
+                log("partner_id", 1),
+            ]
+        );
+        receive_helper(&mut deps, BORN_AT, "owner", 10, breed_msg(1, 3)).unwrap();
+        let handle_result = receive_helper(&mut deps, BORN_AT, "owner", 10, breed_msg(1, 3));
+        match handle_result {
+            Err(StdError::Unauthorized { .. }) => {}
+            _ => panic!("Expected Unauthorized error"),
+        }
+
+        let query_result = query(&deps, QueryMsg::Lineage { pet_id: 1 }).unwrap();
+        match from_binary(&query_result).unwrap() {
+            QueryAnswer::Lineage {
+                generation,
+                children,
+                ..
+            } => {
+                assert_eq!(generation, 0);
+                assert_eq!(children, vec![4, 5]);
+            }
+            _ => panic!("Unexpected query answer"),
+        }
+        let query_result = query(&deps, QueryMsg::Lineage { pet_id: 5 }).unwrap();
+        match from_binary(&query_result).unwrap() {
+            QueryAnswer::Lineage {
+                generation,
+                parents,
+                ..
+            } => {
+                assert_eq!(generation, 1);
+                assert_eq!(parents, vec![1, 3]);
+            }
+            _ => panic!("Unexpected query answer"),
+        }
+
+        // Parents kept alive by their escrow can breed, the FOOD they used is burned
+        for pet_id in 1..=2 {
+            let deposit_msg = ReceiveMsg::DepositEscrow { pet_id };
+            receive_helper(&mut deps, BORN_AT, "owner", 10, deposit_msg).unwrap();
+        }
+        let now = BORN_AT + 3 * FOUR_HOURS;
+        let result = receive_helper(&mut deps, now, "owner", 10, breed_msg(1, 2)).unwrap();
+        let burn_msg = |amount| {
+            FoodHandleMsg::Burn {
+                amount: Uint128(amount),
+                padding: None,
+            }
+            .to_cosmos_msg("food_hash".to_string(), HumanAddr("food".to_string()), None)
+            .unwrap()
+        };
+        let used = |pet_id| 10 - load_pet(&deps.storage, pet_id).unwrap().escrow.u128();
+        assert_eq!(
+            result.messages,
+            vec![burn_msg(used(1)), burn_msg(used(2)), burn_msg(10)]
+        );
+    }
+
+    #[test]
+    fn test_set_feeding_config() {
+        let mut deps = init_helper();
+
+        let handle_msg = HandleMsg::SetFeedingConfig {
+            starvation_window: Some(60),
+            time_per_food: None,
+            max_satiation: None,
+        };
+        let handle_result = handle(&mut deps, mock_env("owner", &[]), handle_msg.clone());
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("This is an admin command"));
+
+        handle(&mut deps, mock_env("admin", &[]), handle_msg).unwrap();
+        let config_state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(config_state.starvation_window, 60);
+        assert_eq!(config_state.time_per_food, FOUR_HOURS);
+
+        let pet_id = create_pet_helper(&mut deps, "owner", "Quick");
+        let pet = query_pet_helper(&deps, pet_id, BORN_AT);
+        assert_eq!(pet.full_until, BORN_AT + pet.traits.satiation_time(60));
+
+        let handle_msg = HandleMsg::SetFeedingConfig {
+            starvation_window: None,
+            time_per_food: Some(0),
+            max_satiation: None,
+        };
+        let handle_result = handle(&mut deps, mock_env("admin", &[]), handle_msg);
+        let error = extract_error_msg(handle_result);
+        assert_eq!(
+            error,
+            "Starvation window and time per FOOD must be positive"
+        );
+    }
+
+    #[test]
+    fn test_set_decay_config() {
+        let mut deps = init_helper();
+
+        let handle_msg = HandleMsg::SetDecayConfig {
+            happiness_decay: Some(10),
+            cleanliness_decay: None,
+            energy_decay: Some(MAX_STAT),
+        };
+        let handle_result = handle(&mut deps, mock_env("owner", &[]), handle_msg.clone());
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("This is an admin command"));
+
+        handle(&mut deps, mock_env("admin", &[]), handle_msg).unwrap();
+        let config_state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(config_state.happiness_decay, 10);
+        assert_eq!(config_state.cleanliness_decay, DEFAULT_CLEANLINESS_DECAY);
+        assert_eq!(config_state.energy_decay, MAX_STAT);
+
+        let handle_msg = HandleMsg::SetDecayConfig {
+            happiness_decay: None,
+            cleanliness_decay: Some(MAX_STAT + 1),
+            energy_decay: None,
+        };
+        let handle_result = handle(&mut deps, mock_env("admin", &[]), handle_msg);
+        let error = extract_error_msg(handle_result);
+        assert_eq!(error, "Decay can't be more than 100 points per hour");
+    }
+
+    // Query tests
+
+    #[test]
+    fn test_query_pets() {
+        let mut deps = init_helper();
+        create_pet_helper(&mut deps, "other", "Spot");
+        create_pet_helper(&mut deps, "owner", "Fluffy");
+        create_pet_helper(&mut deps, "owner", "Nibbles");
+
+        let query_msg = QueryMsg::Pets {
+            owner: HumanAddr("owner".to_string()),
+            now: BORN_AT,
+            page: Some(1),
+            page_size: 2,
+        };
+        let pets = match from_binary(&query(&deps, query_msg).unwrap()).unwrap() {
+            QueryAnswer::Pets { pets } => pets,
+            _ => panic!("Unexpected query answer"),
+        };
+        assert_eq!(pets.len(), 1);
+        assert_eq!(pets[0].id, 4);
+        assert_eq!(pets[0].name, "Nibbles");
+
+        let query_msg = QueryMsg::Pets {
+            owner: HumanAddr("nobody".to_string()),
+            now: BORN_AT,
+            page: None,
+            page_size: 10,
+        };
+        let pets = match from_binary(&query(&deps, query_msg).unwrap()).unwrap() {
+            QueryAnswer::Pets { pets } => pets,
+            _ => panic!("Unexpected query answer"),
+        };
+        assert!(pets.is_empty());
+    }
+
+    #[test]
+    fn test_query_status() {
+        let mut deps = init_helper();
+        average_metabolism_helper(&mut deps);
+        let fed_at = BORN_AT + FOUR_HOURS / 2;
+        feed_helper(&mut deps, fed_at, "owner", 1, 3).unwrap();
+        feed_helper(&mut deps, fed_at + 10, "owner", 1, 4).unwrap();
+
+        let status_helper = |now| {
+            let query_msg = QueryMsg::Status { pet_id: 1, now };
+            match from_binary(&query(&deps, query_msg).unwrap()).unwrap() {
+                QueryAnswer::Status { status } => status,
+                _ => panic!("Unexpected query answer"),
+            }
+        };
+
+        let status = status_helper(fed_at + 10);
+        assert!(status.is_alive);
+        assert_eq!(status.owner, HumanAddr("owner".to_string()));
+        assert_eq!(status.born_at, BORN_AT);
+        assert_eq!(status.full_until, fed_at + 10 + FOUR_HOURS);
+        assert_eq!(status.seconds_left, FOUR_HOURS);
+        assert_eq!(status.died_at, None);
+        assert_eq!(status.food_eaten, Uint128(7));
+        assert_eq!(status.feed_count, 2);
+        assert_eq!(status.stage, LifeStage::Baby);
+        assert_eq!(status.streak, 1);
+        assert_eq!(
+            status.wellbeing,
+            Wellbeing {
+                hunger: 100,
+                happiness: 100 - 8,
+                cleanliness: 100 - 6,
+                energy: 100 - 10,
+                feeding_efficiency: 50 + (92 + 94 + 90) * 50 / 300,
+            }
+        );
+        assert_eq!(status.food_token_addr, HumanAddr("food".to_string()));
+
+        // Death is reported even before anyone records it
+        let status = status_helper(fed_at + 11 + FOUR_HOURS);
+        assert!(!status.is_alive);
+        assert_eq!(status.seconds_left, 0);
+        assert_eq!(status.died_at, Some(fed_at + 10 + FOUR_HOURS));
+    }
+
+    #[test]
+    fn test_accessories() {
+        let mut deps = init_helper();
+
+        let accessory_helper = |deps: &mut Extern<MockStorage, MockApi, MockQuerier>,
+                                contract: &str,
+                                standard,
+                                stat,
+                                decay_percent| {
+            let handle_msg = HandleMsg::SetAccessory {
+                contract: HumanAddr(contract.to_string()),
+                code_hash: format!("{}_hash", contract),
+                standard,
+                stat,
+                decay_percent,
+            };
+            handle(deps, mock_env("admin", &[]), handle_msg).unwrap()
+        };
+        accessory_helper(
+            &mut deps,
+            "blanket",
+            TokenStandard::Snip20,
+            StatKind::Energy,
+            40,
+        );
+        let result = accessory_helper(
+            &mut deps,
+            "collar",
+            TokenStandard::Snip721,
+            StatKind::Happiness,
+            0,
+        );
+        let register_msg = Snip721HandleMsg::RegisterReceiveNft {
+            code_hash: mock_env("admin", &[]).contract_code_hash,
+            also_implements_batch_receive_nft: Some(false),
+            padding: None,
+        }
+        .to_cosmos_msg(
+            "collar_hash".to_string(),
+            HumanAddr("collar".to_string()),
+            None,
+        )
+        .unwrap();
+        assert_eq!(result.messages, vec![register_msg]);
+
+        let handle_msg = HandleMsg::SetAccessory {
+            contract: HumanAddr("curse".to_string()),
+            code_hash: "curse_hash".to_string(),
+            standard: TokenStandard::Snip20,
+            stat: StatKind::Energy,
+            decay_percent: MAX_DECAY_PERCENT + 1,
+        };
+        let handle_result = handle(&mut deps, mock_env("admin", &[]), handle_msg);
+        let error = extract_error_msg(handle_result);
+        assert_eq!(error, "Decay percent can't be more than 200");
+
+        let add_item_msg = to_binary(&ReceiveMsg::AddItem { pet_id: 1 }).unwrap();
+        let handle_result = receive_helper(
+            &mut deps,
+            BORN_AT,
+            "owner",
+            1,
+            ReceiveMsg::AddItem { pet_id: 1 },
+        );
+        let error = extract_error_msg(handle_result);
+        assert_eq!(error, "Only accessories can be added to a pet's inventory");
+
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("owner".to_string()),
+            from: HumanAddr("owner".to_string()),
+            amount: Uint128(2),
+            msg: Some(add_item_msg.clone()),
+        };
+        handle(&mut deps, mock_env("blanket", &[]), handle_msg).unwrap();
+        let handle_msg = HandleMsg::ReceiveNft {
+            sender: HumanAddr("stranger".to_string()),
+            token_id: "junk".to_string(),
+            msg: Some(add_item_msg.clone()),
+        };
+        let handle_result = handle(&mut deps, mock_env("collar", &[]), handle_msg);
+        match handle_result {
+            Err(StdError::Unauthorized { .. }) => {}
+            _ => panic!("Expected Unauthorized error"),
+        }
+        let handle_msg = HandleMsg::ReceiveNft {
+            sender: HumanAddr("owner".to_string()),
+            token_id: "gold".to_string(),
+            msg: Some(add_item_msg),
+        };
+        handle(&mut deps, mock_env("collar", &[]), handle_msg).unwrap();
+
+        let equip_helper = |deps: &mut Extern<MockStorage, MockApi, MockQuerier>,
+                            contract: &str,
+                            token_id: Option<&str>| {
+            let mut env = mock_env("owner", &[]);
+            env.block.time = BORN_AT;
+            let handle_msg = HandleMsg::Equip {
+                pet_id: 1,
+                contract: HumanAddr(contract.to_string()),
+                token_id: token_id.map(String::from),
+            };
+            handle(deps, env, handle_msg)
+        };
+        let handle_result = equip_helper(&mut deps, "collar", None);
+        let error = extract_error_msg(handle_result);
+        assert_eq!(error, "Pet 1 doesn't have this item");
+        equip_helper(&mut deps, "collar", Some("gold")).unwrap();
+        equip_helper(&mut deps, "blanket", None).unwrap();
+
+        // Energy decays 2 points an hour instead of 5 under the blanket, happiness doesn't decay at all
+        let now = BORN_AT + 2 * 60 * 60;
+        let query_msg = QueryMsg::Status { pet_id: 1, now };
+        let status = match from_binary(&query(&deps, query_msg).unwrap()).unwrap() {
+            QueryAnswer::Status { status } => status,
+            _ => panic!("Unexpected query answer"),
+        };
+        assert_eq!(status.wellbeing.energy, MAX_STAT - 4);
+        assert_eq!(status.wellbeing.happiness, MAX_STAT);
+        assert_eq!(status.inventory.len(), 2);
+        assert_eq!(status.inventory[0].amount, Uint128(2));
+        assert!(status.inventory[0].equipped);
+
+        let mut env = mock_env("owner", &[]);
+        env.block.time = now;
+        let handle_msg = HandleMsg::Unequip {
+            pet_id: 1,
+            contract: HumanAddr("blanket".to_string()),
+            token_id: None,
+        };
+        let result = handle(&mut deps, env, handle_msg).unwrap();
+        let transfer_msg = FoodHandleMsg::Transfer {
+            recipient: HumanAddr("owner".to_string()),
+            amount: Uint128(2),
+            padding: None,
+        }
+        .to_cosmos_msg(
+            "blanket_hash".to_string(),
+            HumanAddr("blanket".to_string()),
+            None,
+        )
+        .unwrap();
+        assert_eq!(result.messages, vec![transfer_msg]);
+
+        // Decay before the blanket was taken off stays slower
+        let pet = load_pet(&deps.storage, 1).unwrap();
+        assert_eq!(pet.inventory.len(), 1);
+        assert_eq!(
+            pet.energy.value_at(now + 60 * 60, DEFAULT_ENERGY_DECAY),
+            MAX_STAT - 4 - DEFAULT_ENERGY_DECAY
+        );
+
+        // Escrow keeps the pet alive past its countdown, its debit is burned on equip
+        let deposit_msg = ReceiveMsg::DepositEscrow { pet_id: 1 };
+        receive_helper(&mut deps, now, "owner", 10, deposit_msg).unwrap();
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("owner".to_string()),
+            from: HumanAddr("owner".to_string()),
+            amount: Uint128(1),
+            msg: Some(to_binary(&ReceiveMsg::AddItem { pet_id: 1 }).unwrap()),
+        };
+        handle(&mut deps, mock_env("blanket", &[]), handle_msg).unwrap();
+        let mut env = mock_env("owner", &[]);
+        env.block.time = BORN_AT + 3 * FOUR_HOURS;
+        let handle_msg = HandleMsg::Equip {
+            pet_id: 1,
+            contract: HumanAddr("blanket".to_string()),
+            token_id: None,
+        };
+        let result = handle(&mut deps, env, handle_msg).unwrap();
+        let pet = load_pet(&deps.storage, 1).unwrap();
+        let burn_msg = FoodHandleMsg::Burn {
+            amount: Uint128(10 - pet.escrow.u128()),
+            padding: None,
+        }
+        .to_cosmos_msg("food_hash".to_string(), HumanAddr("food".to_string()), None)
+        .unwrap();
+        assert_eq!(result.messages, vec![burn_msg]);
+        assert!(pet.is_alive(BORN_AT + 3 * FOUR_HOURS));
+    }
+
+    #[test]
+    fn test_leaderboard() {
+        let mut deps = init_helper();
+        create_pet_helper(&mut deps, "owner2", "Fido");
+        create_pet_helper(&mut deps, "owner3", "Kit");
+
+        let listing_helper =
+            |deps: &mut Extern<MockStorage, MockApi, MockQuerier>, owner, pet_id| {
+                let handle_msg = HandleMsg::SetListing {
+                    pet_id,
+                    public: true,
+                    padding: None,
+                };
+                handle(deps, mock_env(owner, &[]), handle_msg)
+            };
+        let handle_result = listing_helper(&mut deps, "owner2", 1);
+        match handle_result {
+            Err(StdError::Unauthorized { .. }) => {}
+            _ => panic!("Expected Unauthorized error"),
+        }
+        listing_helper(&mut deps, "owner", 1).unwrap();
+        listing_helper(&mut deps, "owner2", 2).unwrap();
+
+        feed_helper(&mut deps, BORN_AT + 1, "owner", 1, 3).unwrap();
+        feed_helper(&mut deps, BORN_AT + 1, "owner2", 2, 1).unwrap();
+        feed_helper(&mut deps, BORN_AT + STREAK_INTERVAL + 1, "owner2", 2, 1).unwrap();
+        feed_helper(&mut deps, BORN_AT + 1, "owner3", 3, 10).unwrap();
+
+        let leaderboard_helper =
+            |deps: &Extern<MockStorage, MockApi, MockQuerier>, board, page, page_size| {
+                let query_msg = QueryMsg::Leaderboard {
+                    board,
+                    page,
+                    page_size,
+                };
+                from_binary(&query(deps, query_msg)?).map(|answer| match answer {
+                    QueryAnswer::Leaderboard { entries } => entries
+                        .into_iter()
+                        .map(|entry| (entry.rank, entry.pet_id, entry.score.u128()))
+                        .collect::<Vec<_>>(),
+                    _ => panic!("Unexpected query answer"),
+                })
+            };
+        let entries = leaderboard_helper(&deps, Board::FoodEaten, None, 10).unwrap();
+        assert_eq!(entries, vec![(1, 1, 3), (2, 2, 2)]);
+        let entries = leaderboard_helper(&deps, Board::Streak, None, 10).unwrap();
+        assert_eq!(entries, vec![(1, 2, 2), (2, 1, 1)]);
+        let entries = leaderboard_helper(&deps, Board::Streak, Some(1), 1).unwrap();
+        assert_eq!(entries, vec![(2, 1, 1)]);
+        let entries = leaderboard_helper(&deps, Board::Streak, Some(u32::MAX), 50).unwrap();
+        assert_eq!(entries, vec![]);
+        assert!(leaderboard_helper(&deps, Board::Streak, None, 0).is_err());
+        assert!(leaderboard_helper(&deps, Board::Streak, Some(2), u32::MAX).is_err());
+
+        // Settling keeps the life of a dead pet on the board
+        let full_until = load_pet(&deps.storage, 1).unwrap().full_until;
+        let mut env = mock_env("keeper", &[]);
+        env.block.time = full_until + 1;
+        handle(&mut deps, env, HandleMsg::Settle { pet_id: 1 }).unwrap();
+        let pet = load_pet(&deps.storage, 1).unwrap();
+        assert_eq!(pet.longest_life, full_until - BORN_AT);
+
+        // Scores of living pets are the ones of their last feed
+        let life = (full_until - BORN_AT) as u128;
+        let entries = leaderboard_helper(&deps, Board::Lifespan, None, 10).unwrap();
+        assert_eq!(
+            entries,
+            vec![(1, 1, life), (2, 2, (STREAK_INTERVAL + 1) as u128)]
+        );
+
+        // New owners have to list the pet again
+        let handle_msg = HandleMsg::TransferNft {
+            recipient: HumanAddr("buyer".to_string()),
+            token_id: "2".to_string(),
+            memo: None,
+            padding: None,
+        };
+        handle(&mut deps, mock_env("owner2", &[]), handle_msg).unwrap();
+        let entries = leaderboard_helper(&deps, Board::Age, None, 10).unwrap();
+        assert_eq!(entries, vec![(1, 1, life)]);
+
+        // Reviving brings the scores up to date right away
+        let revive_msg = ReceiveMsg::Revive { pet_id: 1 };
+        receive_helper(&mut deps, full_until + 1, "owner", 3, revive_msg).unwrap();
+        let entries = leaderboard_helper(&deps, Board::Age, None, 10).unwrap();
+        assert_eq!(entries, vec![(1, 1, life + 1)]);
+
+        let handle_msg = HandleMsg::SetListing {
+            pet_id: 1,
+            public: false,
+            padding: None,
+        };
+        handle(&mut deps, mock_env("owner", &[]), handle_msg).unwrap();
+        let entries = leaderboard_helper(&deps, Board::FoodEaten, None, 10).unwrap();
+        assert_eq!(entries, vec![]);
+
+        // Only the best pets are kept on a board
+        for pet_id in 0..=LEADERBOARD_SIZE as u64 {
+            let score = Some(Uint128(pet_id as u128));
+            update_leaderboard(&mut deps.storage, b"test", pet_id, score).unwrap();
+        }
+        let entries = read_leaderboard(&deps.storage, b"test").unwrap();
+        assert_eq!(entries.len(), LEADERBOARD_SIZE);
+        assert_eq!(entries[0].pet_id, LEADERBOARD_SIZE as u64);
+        assert_eq!(entries[LEADERBOARD_SIZE - 1].pet_id, 1);
+    }
+
+    #[test]
+    fn test_transfer_nft() {
+        let mut deps = init_helper();
+
+        let handle_msg = HandleMsg::TransferNft {
+            recipient: HumanAddr("buyer".to_string()),
+            token_id: "1".to_string(),
+            memo: None,
+            padding: None,
+        };
+        let handle_result = handle(&mut deps, mock_env("buyer", &[]), handle_msg.clone());
+        match handle_result {
+            Err(StdError::Unauthorized { .. }) => {}
+            _ => panic!("Expected Unauthorized error"),
+        }
+        handle(&mut deps, mock_env("owner", &[]), handle_msg).unwrap();
+
+        let query_msg = QueryMsg::OwnerOf {
+            token_id: "1".to_string(),
+            viewer: None,
+            include_expired: None,
+        };
+        match from_binary(&query(&deps, query_msg).unwrap()).unwrap() {
+            QueryAnswer::OwnerOf { owner, .. } => {
+                assert_eq!(owner, HumanAddr("buyer".to_string()))
+            }
+            _ => panic!("Unexpected query answer"),
+        }
+
+        // Registered contracts are notified when they receive a pet
+        let handle_msg = HandleMsg::RegisterReceiveNft {
+            code_hash: "market_hash".to_string(),
+            also_implements_batch_receive_nft: None,
+            padding: None,
+        };
+        handle(&mut deps, mock_env("market", &[]), handle_msg).unwrap();
+
+        let handle_msg = HandleMsg::SendNft {
+            contract: HumanAddr("market".to_string()),
+            token_id: "1".to_string(),
+            msg: Some(Binary::from(b"list".to_vec())),
+            memo: None,
+            padding: None,
+        };
+        let result = handle(&mut deps, mock_env("buyer", &[]), handle_msg).unwrap();
+        let receive_msg = Snip721ReceiveMsg::ReceiveNft {
+            sender: HumanAddr("buyer".to_string()),
+            token_id: "1".to_string(),
+            msg: Some(Binary::from(b"list".to_vec())),
+        }
+        .to_cosmos_msg(
+            "market_hash".to_string(),
+            HumanAddr("market".to_string()),
+            None,
+        )
+        .unwrap();
+        assert_eq!(result.messages, vec![receive_msg]);
+
+        let market = deps
+            .api
+            .canonical_address(&HumanAddr("market".to_string()))
+            .unwrap();
+        assert_eq!(read_owner_pets(&deps.storage, &market).unwrap(), vec![1]);
+    }
+
+    #[test]
+    fn test_query_nft_metadata() {
+        let mut deps = init_helper();
+
+        let query_result = query(
+            &deps,
+            QueryMsg::NftInfo {
+                token_id: "1".to_string(),
+            },
+        );
+        let extension = match from_binary(&query_result.unwrap()).unwrap() {
+            QueryAnswer::NftInfo { extension, .. } => extension.unwrap(),
+            _ => panic!("Unexpected query answer"),
+        };
+        assert_eq!(extension.name, Some("Rex".to_string()));
+        let attributes = extension.attributes.unwrap();
+        assert_eq!(attributes[3], trait_attribute("stage", "egg".to_string()));
+
+        let handle_msg = HandleMsg::SetViewingKey {
+            key: "owner_key".to_string(),
+            padding: None,
+        };
+        handle(&mut deps, mock_env("owner", &[]), handle_msg).unwrap();
+
+        let private_metadata_msg = |viewer| QueryMsg::PrivateMetadata {
+            token_id: "1".to_string(),
+            viewer,
+        };
+        let query_result = query(&deps, private_metadata_msg(None));
+        match query_result {
+            Err(StdError::Unauthorized { .. }) => {}
+            _ => panic!("Expected Unauthorized error"),
+        }
+
+        let viewer = ViewerInfo {
+            address: HumanAddr("owner".to_string()),
+            viewing_key: "owner_key".to_string(),
+        };
+        let query_result = query(&deps, private_metadata_msg(Some(viewer))).unwrap();
+        let extension = match from_binary(&query_result).unwrap() {
+            QueryAnswer::PrivateMetadata { extension, .. } => extension.unwrap(),
+            _ => panic!("Unexpected query answer"),
+        };
+        let attributes = extension.attributes.unwrap();
+        assert_eq!(
+            attributes[2],
+            trait_attribute("born_at", BORN_AT.to_string())
+        );
+    }
+
+    #[test]
+    fn test_handle_create_viewing_key() {
+        let mut deps = init_helper();
+
+        let handle_msg = HandleMsg::CreateViewingKey {
+            entropy: "entropy".to_string(),
+            padding: None,
+        };
+        let result = handle(&mut deps, mock_env("owner", &[]), handle_msg).unwrap();
+        let key = match from_binary(&result.data.unwrap()).unwrap() {
+            HandleAnswer::CreateViewingKey { key } => key,
+            _ => panic!("Unexpected handle answer"),
+        };
+
+        let owner = deps
+            .api
+            .canonical_address(&HumanAddr("owner".to_string()))
+            .unwrap();
+        let saved_vk = read_viewing_key(&deps.storage, &owner).unwrap();
+        assert!(key.check_viewing_key(saved_vk.as_slice()));
+    }
+
+    #[test]
+    fn test_query_feeding_history() {
+        let mut deps = init_helper();
+        average_metabolism_helper(&mut deps);
+        let fed_at = BORN_AT + 60;
+        feed_helper(&mut deps, fed_at, "owner", 1, 3).unwrap();
+        feed_helper(&mut deps, fed_at + FOUR_HOURS + 61, "owner", 1, 5).unwrap();
+
+        for (sender, key) in &[("owner", "owner_key"), ("other", "other_key")] {
+            let handle_msg = HandleMsg::SetViewingKey {
+                key: key.to_string(),
+                padding: None,
+            };
+            handle(&mut deps, mock_env(*sender, &[]), handle_msg).unwrap();
+        }
+
+        let history_msg = |address: &str, key: &str| QueryMsg::FeedingHistory {
+            pet_id: 1,
+            address: HumanAddr(address.to_string()),
+            key: key.to_string(),
+            page: None,
+            page_size: 10,
+        };
+
+        let query_result = query(&deps, history_msg("owner", "wrong_key")).unwrap();
+        match from_binary(&query_result).unwrap() {
+            QueryAnswer::ViewingKeyError { .. } => {}
+            _ => panic!("Expected ViewingKeyError"),
+        }
+
+        let query_result = query(&deps, history_msg("other", "other_key"));
+        match query_result {
+            Err(StdError::Unauthorized { .. }) => {}
+            _ => panic!("Expected Unauthorized error"),
+        }
+
+        let query_result = query(&deps, history_msg("owner", "owner_key")).unwrap();
+        let events = match from_binary(&query_result).unwrap() {
+            QueryAnswer::FeedingHistory { events } => events,
+            _ => panic!("Unexpected query answer"),
+        };
+        let expected = vec![
+            FeedEvent {
+                time: fed_at + FOUR_HOURS + 61,
+                feeder: HumanAddr("owner".to_string()),
+                food: HumanAddr("food".to_string()),
+                amount: Uint128(5),
+                refunded: true,
+            },
+            FeedEvent {
+                time: fed_at,
+                feeder: HumanAddr("owner".to_string()),
+                food: HumanAddr("food".to_string()),
+                amount: Uint128(3),
+                refunded: false,
+            },
+        ];
+        assert_eq!(events, expected);
+    }
+}
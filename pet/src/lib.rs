@@ -1,6 +1,9 @@
 pub mod contract;
 pub mod msg;
+mod rand;
 pub mod state;
+mod utils;
+mod viewing_key;
 
 #[cfg(target_arch = "wasm32")]
 mod wasm {
@@ -1,40 +1,683 @@
-use cosmwasm_std::{HumanAddr, Uint128};
+use cosmwasm_std::{Binary, HumanAddr, Uint128};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::state::{
+    Caretaker, FeedEvent, Food, Illness, Item, LifeStage, Memorial, StatKind, TokenStandard, Traits,
+};
+use crate::viewing_key::ViewingKey;
+
 // Food
 
 #[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum FoodHandleMsg {
-    BurnFrom {
-        owner: HumanAddr,
+    Transfer {
+        recipient: HumanAddr,
+        amount: Uint128,
+        padding: Option<String>,
+    },
+    Burn {
         amount: Uint128,
         padding: Option<String>,
     },
+    RegisterReceive {
+        code_hash: String,
+        padding: Option<String>,
+    },
+}
+
+// SNIP-721
+
+/// Handles of SNIP-721 accessory contracts used by the Pet.
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Snip721HandleMsg {
+    TransferNft {
+        recipient: HumanAddr,
+        token_id: String,
+        memo: Option<String>,
+        padding: Option<String>,
+    },
+    RegisterReceiveNft {
+        code_hash: String,
+        also_implements_batch_receive_nft: Option<bool>,
+        padding: Option<String>,
+    },
+}
+
+/// Callback sent to contracts which registered with `RegisterReceiveNft`.
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Snip721ReceiveMsg {
+    ReceiveNft {
+        sender: HumanAddr,
+        token_id: String,
+        msg: Option<Binary>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ViewerInfo {
+    pub address: HumanAddr,
+    pub viewing_key: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Metadata {
+    pub token_uri: Option<String>,
+    pub extension: Option<Extension>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Extension {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub image: Option<String>,
+    pub attributes: Option<Vec<Trait>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Trait {
+    pub display_type: Option<String>,
+    pub trait_type: Option<String>,
+    pub value: String,
 }
 
 // Pet
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InitMsg {
+    pub admin: Option<HumanAddr>,
+    pub prng_seed: Binary,
     pub food_token_code_hash: String,
     pub food_token_addr: HumanAddr,
+    /// Words pet names and bios can't contain, matched case-insensitively.
+    pub banned_words: Option<Vec<String>>,
+    /// Seconds a newly created pet can live without food. Default: 4 hours.
+    pub starvation_window: Option<u64>,
+    /// Seconds of satiation bought with a single FOOD unit. Default: 4 hours.
+    pub time_per_food: Option<u64>,
+    /// Maximum number of seconds a pet can be fed ahead. Default: 4 hours.
+    pub max_satiation: Option<u64>,
+    /// Points of happiness lost every hour. Default: 4.
+    pub happiness_decay: Option<u64>,
+    /// Points of cleanliness lost every hour. Default: 3.
+    pub cleanliness_decay: Option<u64>,
+    /// Points of energy lost every hour. Default: 5.
+    pub energy_decay: Option<u64>,
+    /// Whether dead pets can be revived. Default: true.
+    pub revival_enabled: Option<bool>,
+    /// Multiple of the FOOD needed to fill a starvation window that reviving costs. Default: 3.
+    pub revival_multiplier: Option<u64>,
+    /// FOOD paid from the pool for settling a dead pet. Default: 0.
+    pub settle_bounty: Option<Uint128>,
+    /// FOOD burned to put a pet into hibernation. Default: 1.
+    pub hibernation_fee: Option<Uint128>,
+    /// Maximum number of seconds a pet can hibernate at once. Default: 3 days.
+    pub max_hibernation: Option<u64>,
+    /// FOOD burned for breeding two pets. Default: 10.
+    pub breeding_fee: Option<Uint128>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum HandleMsg {
-    Feed {},
+    /// SNIP-20 callback, sent by a food contract when someone `Send`s food to the Pet.
+    Receive {
+        sender: HumanAddr,
+        from: HumanAddr,
+        amount: Uint128,
+        msg: Option<Binary>,
+    },
+    /// SNIP-721 callback, sent by an accessory contract when someone sends an item to the Pet.
+    ReceiveNft {
+        sender: HumanAddr,
+        token_id: String,
+        msg: Option<Binary>,
+    },
+    CreatePet {
+        name: String,
+    },
+    /// First step of a transfer, the pet changes hands once `new_owner` accepts it.
+    TransferOwnership {
+        pet_id: u64,
+        new_owner: HumanAddr,
+    },
+    AcceptOwnership {
+        pet_id: u64,
+    },
+    /// Withdraws a pending transfer, only the owner can cancel it.
+    CancelOwnershipTransfer {
+        pet_id: u64,
+    },
+    /// Records the death of a starved pet, anyone can call it. Also applies due escrow debits,
+    /// the bounty is only paid for deaths.
+    Settle {
+        pet_id: u64,
+    },
+    /// Allows `pet_id` to be bred with another owner's `partner_id`, `None` withdraws the consent.
+    ApproveBreeding {
+        pet_id: u64,
+        partner_id: Option<u64>,
+    },
+    /// Ends hibernation early, the countdown resumes with the time left.
+    Wake {
+        pet_id: u64,
+    },
+    /// Cheers the pet up, at the cost of some energy. Games are played just for fun here,
+    /// wagers are sent through Receive.
+    Play {
+        pet_id: u64,
+        game: Option<Game>,
+    },
+    Clean {
+        pet_id: u64,
+    },
+    Sleep {
+        pet_id: u64,
+    },
+    /// Returns escrowed FOOD to the owner, all of it if `amount` is not set.
+    WithdrawEscrow {
+        pet_id: u64,
+        amount: Option<Uint128>,
+        padding: Option<String>,
+    },
+    /// Puts on an item from the pet's inventory, `token_id` is only set for SNIP-721 items.
+    Equip {
+        pet_id: u64,
+        contract: HumanAddr,
+        token_id: Option<String>,
+    },
+    /// Takes an item off the pet, or out of its inventory, and returns it to the owner.
+    Unequip {
+        pet_id: u64,
+        contract: HumanAddr,
+        token_id: Option<String>,
+    },
+    /// Updates the fields which are set, an empty bio or avatar URI clears it.
+    SetMetadata {
+        pet_id: u64,
+        name: Option<String>,
+        bio: Option<String>,
+        avatar_uri: Option<String>,
+        padding: Option<String>,
+    },
+    /// Lists the pet on the public leaderboards, or takes it off them.
+    SetListing {
+        pet_id: u64,
+        public: bool,
+        padding: Option<String>,
+    },
+    AddCaretaker {
+        pet_id: u64,
+        address: HumanAddr,
+        expiration: Option<u64>,
+    },
+    RemoveCaretaker {
+        pet_id: u64,
+        address: HumanAddr,
+    },
+
+    // SNIP-721, token ids are pet ids
+    TransferNft {
+        recipient: HumanAddr,
+        token_id: String,
+        memo: Option<String>,
+        padding: Option<String>,
+    },
+    SendNft {
+        contract: HumanAddr,
+        token_id: String,
+        msg: Option<Binary>,
+        memo: Option<String>,
+        padding: Option<String>,
+    },
+    RegisterReceiveNft {
+        code_hash: String,
+        also_implements_batch_receive_nft: Option<bool>,
+        padding: Option<String>,
+    },
+    CreateViewingKey {
+        entropy: String,
+        padding: Option<String>,
+    },
+    SetViewingKey {
+        key: String,
+        padding: Option<String>,
+    },
+
+    // Admin
+    SetFeedingConfig {
+        starvation_window: Option<u64>,
+        time_per_food: Option<u64>,
+        max_satiation: Option<u64>,
+    },
+    /// Points every stat loses per hour, at most `MAX_STAT`. New rates also apply to the time
+    /// since each stat was last changed, they aren't settled for every pet beforehand.
+    SetDecayConfig {
+        happiness_decay: Option<u64>,
+        cleanliness_decay: Option<u64>,
+        energy_decay: Option<u64>,
+    },
+    SetRevivalConfig {
+        enabled: Option<bool>,
+        multiplier: Option<u64>,
+    },
+    SetSettleBounty {
+        bounty: Uint128,
+    },
+    SetHibernationConfig {
+        fee: Option<Uint128>,
+        max_duration: Option<u64>,
+    },
+    SetBreedingFee {
+        fee: Uint128,
+    },
+    /// Adds a food to the whitelist, or updates an existing one.
+    SetFood {
+        contract: HumanAddr,
+        code_hash: String,
+        nutrition: u64,
+        burn: bool,
+    },
+    RemoveFood {
+        contract: HumanAddr,
+    },
+    /// Sends food which was eaten but not burned to `recipient`, all of it if `amount` is not set.
+    /// Also works for foods which were removed from the whitelist.
+    WithdrawHeldFood {
+        contract: HumanAddr,
+        code_hash: String,
+        recipient: HumanAddr,
+        amount: Option<Uint128>,
+    },
+    SetMedicine {
+        contract: HumanAddr,
+        code_hash: String,
+    },
+    /// Adds an accessory to the whitelist, or updates an existing one.
+    /// Items already in inventories keep their effect.
+    SetAccessory {
+        contract: HumanAddr,
+        code_hash: String,
+        standard: TokenStandard,
+        stat: StatKind,
+        decay_percent: u64,
+    },
+    RemoveAccessory {
+        contract: HumanAddr,
+    },
+    /// Replaces the list of words pet names and bios can't contain.
+    SetBannedWords {
+        words: Vec<String>,
+    },
+}
+
+/// Messages which can be attached to the `msg` field of a SNIP-20 `Send`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiveMsg {
+    Feed {
+        pet_id: u64,
+    },
+    /// Brings a dead pet back, the exact revival cost has to be sent.
+    Revive {
+        pet_id: u64,
+    },
+    /// Adds the FOOD sent to the bounty pool.
+    FundPool {},
+    /// Freezes the countdown until `until`, the exact hibernation fee has to be sent.
+    /// Pet has to be fed since its last hibernation.
+    Hibernate {
+        pet_id: u64,
+        until: u64,
+    },
+    /// Breeds two adult pets into a new one owned by the sender, the exact breeding fee has to
+    /// be sent. `partner_id` has to belong to the sender too, or have approved breeding with `pet_id`.
+    Breed {
+        pet_id: u64,
+        partner_id: u64,
+        name: String,
+    },
+    /// Prepays FOOD which feeds the pet automatically, only the primary FOOD is accepted.
+    /// Only the owner can deposit, whatever is left is returned when the pet changes owners.
+    DepositEscrow {
+        pet_id: u64,
+    },
+    /// Plays a game with the sent FOOD as the wager, wins are paid from the pool.
+    Play {
+        pet_id: u64,
+        game: Game,
+    },
+    /// Adds the sent accessory to the pet's inventory, works with SNIP-721 accessories too.
+    /// Only the owner and caretakers can add items.
+    AddItem {
+        pet_id: u64,
+    },
+    /// Cures an ill pet, has to be sent with exactly one unit of the medicine token.
+    Treat {
+        pet_id: u64,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HandleAnswer {
+    CreatePet { pet_id: u64 },
+    TransferOwnership { status: ResponseStatus },
+    AcceptOwnership { status: ResponseStatus },
+    CancelOwnershipTransfer { status: ResponseStatus },
+    Settle { bounty: Uint128 },
+    Wake { status: ResponseStatus },
+    ApproveBreeding { status: ResponseStatus },
+    Breed { pet_id: u64 },
+    SetMetadata { status: ResponseStatus },
+    SetListing { status: ResponseStatus },
+    Equip { status: ResponseStatus },
+    Unequip { status: ResponseStatus },
+    WithdrawEscrow { amount: Uint128 },
+    TransferNft { status: ResponseStatus },
+    SendNft { status: ResponseStatus },
+    RegisterReceiveNft { status: ResponseStatus },
+    Play { status: ResponseStatus },
+    Game { won: bool, payout: Uint128 },
+    Clean { status: ResponseStatus },
+    Sleep { status: ResponseStatus },
+    AddCaretaker { status: ResponseStatus },
+    RemoveCaretaker { status: ResponseStatus },
+    CreateViewingKey { key: ViewingKey },
+    SetViewingKey { status: ResponseStatus },
+    SetFeedingConfig { status: ResponseStatus },
+    SetDecayConfig { status: ResponseStatus },
+    SetRevivalConfig { status: ResponseStatus },
+    SetSettleBounty { status: ResponseStatus },
+    SetHibernationConfig { status: ResponseStatus },
+    SetBreedingFee { status: ResponseStatus },
+    SetFood { status: ResponseStatus },
+    RemoveFood { status: ResponseStatus },
+    WithdrawHeldFood { amount: Uint128 },
+    SetMedicine { status: ResponseStatus },
+    SetAccessory { status: ResponseStatus },
+    RemoveAccessory { status: ResponseStatus },
+    SetBannedWords { status: ResponseStatus },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Game {
+    CoinFlip {
+        guess: Coin,
+    },
+    /// Pet hides in one of four spots, numbered from 0.
+    HideAndSeek {
+        spot: u8,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Coin {
+    Heads,
+    Tails,
 }
 
+/// Queries don't have access to the block time, so caller provides
+/// current time (`now`, seconds since epoch) to check the countdown against.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
-    Query {},
+    Config {},
+    Foods {},
+    Pet {
+        pet_id: u64,
+        now: u64,
+    },
+    Pets {
+        owner: HumanAddr,
+        now: u64,
+        page: Option<u32>,
+        page_size: u32,
+    },
+    Status {
+        pet_id: u64,
+        now: u64,
+    },
+    Caretakers {
+        pet_id: u64,
+        now: u64,
+    },
+    Lineage {
+        pet_id: u64,
+    },
+    /// Current illness and past ones, latest first.
+    Health {
+        pet_id: u64,
+        page: Option<u32>,
+        page_size: u32,
+    },
+    /// FOOD left in the pet's escrow at `now`.
+    Escrow {
+        pet_id: u64,
+        now: u64,
+    },
+    /// Memorials of dead pets, latest first, only those owned by `owner` if it's set.
+    Graveyard {
+        owner: Option<HumanAddr>,
+        page: Option<u32>,
+        page_size: u32,
+    },
+    /// Ranks the pets whose owners listed them publicly, by their scores as of
+    /// the last time they were fed or settled. Only the best 100 are ranked.
+    Leaderboard {
+        board: Board,
+        page: Option<u32>,
+        page_size: u32,
+    },
+    FeedingHistory {
+        pet_id: u64,
+        address: HumanAddr,
+        key: String,
+        page: Option<u32>,
+        page_size: u32,
+    },
+
+    // SNIP-721, owners are public so `viewer` is only needed for private metadata
+    OwnerOf {
+        token_id: String,
+        viewer: Option<ViewerInfo>,
+        include_expired: Option<bool>,
+    },
+    NftInfo {
+        token_id: String,
+    },
+    PrivateMetadata {
+        token_id: String,
+        viewer: Option<ViewerInfo>,
+    },
+}
+
+impl QueryMsg {
+    pub fn get_validation_params(&self) -> (Vec<&HumanAddr>, ViewingKey) {
+        match self {
+            Self::FeedingHistory { address, key, .. } => (vec![address], ViewingKey(key.clone())),
+            Self::PrivateMetadata {
+                viewer: Some(viewer),
+                ..
+            } => (
+                vec![&viewer.address],
+                ViewingKey(viewer.viewing_key.clone()),
+            ),
+            _ => panic!("This query type does not require authentication"),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-pub struct QueryResponse {
+#[serde(rename_all = "snake_case")]
+pub enum QueryAnswer {
+    Config {
+        admin: HumanAddr,
+        food_token_addr: HumanAddr,
+        starvation_window: u64,
+        time_per_food: u64,
+        max_satiation: u64,
+        happiness_decay: u64,
+        cleanliness_decay: u64,
+        energy_decay: u64,
+        revival_enabled: bool,
+        /// FOOD it currently takes to revive a pet.
+        revival_cost: Uint128,
+        settle_bounty: Uint128,
+        pool: Uint128,
+        hibernation_fee: Uint128,
+        max_hibernation: u64,
+        breeding_fee: Uint128,
+        medicine_addr: Option<HumanAddr>,
+    },
+    Foods {
+        foods: Vec<Food>,
+    },
+    Pet {
+        pet: PetInfo,
+    },
+    Pets {
+        pets: Vec<PetInfo>,
+    },
+    Status {
+        status: PetStatus,
+    },
+    Caretakers {
+        caretakers: Vec<Caretaker>,
+    },
+    Lineage {
+        pet_id: u64,
+        generation: u64,
+        parents: Vec<u64>,
+        children: Vec<u64>,
+    },
+    Health {
+        ill_since: Option<u64>,
+        illnesses: Vec<Illness>,
+    },
+    Escrow {
+        amount: Uint128,
+    },
+    Graveyard {
+        memorials: Vec<Memorial>,
+    },
+    Leaderboard {
+        entries: Vec<LeaderboardEntry>,
+    },
+    FeedingHistory {
+        events: Vec<FeedEvent>,
+    },
+    OwnerOf {
+        owner: HumanAddr,
+        approvals: Vec<Cw721Approval>,
+    },
+    NftInfo {
+        token_uri: Option<String>,
+        extension: Option<Extension>,
+    },
+    PrivateMetadata {
+        token_uri: Option<String>,
+        extension: Option<Extension>,
+    },
+
+    ViewingKeyError {
+        msg: String,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PetInfo {
+    pub id: u64,
+    pub name: String,
+    pub owner: HumanAddr,
     pub is_alive: bool,
+    /// Block time (seconds since epoch) until which the pet is full.
+    pub full_until: u64,
+    pub traits: Traits,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PetStatus {
+    pub id: u64,
+    pub name: String,
+    pub bio: Option<String>,
+    pub avatar_uri: Option<String>,
+    pub owner: HumanAddr,
+    /// Address a transfer is waiting to be accepted by.
+    pub pending_owner: Option<HumanAddr>,
+    pub is_alive: bool,
+    pub born_at: u64,
+    pub full_until: u64,
+    /// Seconds left before starvation, zero if the pet is dead.
+    pub seconds_left: u64,
+    pub died_at: Option<u64>,
+    /// Total amount of food units the pet has eaten.
+    pub food_eaten: Uint128,
+    pub feed_count: u64,
+    pub stage: LifeStage,
+    /// Number of consecutive on-time feeds.
+    pub streak: u64,
+    pub wellbeing: Wellbeing,
+    /// Number of times the pet died and was revived.
+    pub deaths: u64,
+    /// End of the current hibernation, the countdown is frozen until then.
+    pub hibernating_until: Option<u64>,
+    /// Time the pet fell ill, `None` if it's healthy.
+    pub ill_since: Option<u64>,
+    pub inventory: Vec<Item>,
+    pub traits: Traits,
+    pub food_token_addr: HumanAddr,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Board {
+    /// Current age in seconds, pets stop aging when they die.
+    Age,
+    /// Longest life in seconds, ever since the pet was born or revived.
+    Lifespan,
+    FoodEaten,
+    /// Longest streak of on-time feeds.
+    Streak,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LeaderboardEntry {
+    /// Starts at 1, pets with equal scores are ranked by id.
+    pub rank: u32,
+    pub pet_id: u64,
+    pub name: String,
+    pub owner: HumanAddr,
+    pub score: Uint128,
+}
+
+/// Pets have no transfer approvals, this is only here to match the SNIP-721 answer.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Cw721Approval {
+    pub spender: HumanAddr,
+    pub expires: u64,
+}
+
+/// Wellbeing stats, each between 0 and 100.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Wellbeing {
+    /// How full the pet is, it starves when this drops to zero.
+    pub hunger: u64,
+    pub happiness: u64,
+    pub cleanliness: u64,
+    pub energy: u64,
+    /// Percentage of food nutrition the pet currently gets out of a meal.
+    pub feeding_efficiency: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseStatus {
+    Success,
+    Failure,
 }
@@ -1,23 +1,1078 @@
-use cosmwasm_std::{HumanAddr, Storage};
-use cosmwasm_storage::{singleton, singleton_read, ReadonlySingleton, Singleton};
+use cosmwasm_std::{
+    Api, Binary, CanonicalAddr, HumanAddr, ReadonlyStorage, StdError, StdResult, Storage, Uint128,
+};
+use cosmwasm_storage::{
+    bucket, bucket_read, singleton, singleton_read, Bucket, PrefixedStorage, ReadonlyBucket,
+    ReadonlyPrefixedStorage, ReadonlySingleton, Singleton,
+};
 use schemars::JsonSchema;
+use secret_toolkit::storage::{AppendStore, AppendStoreMut};
 use serde::{Deserialize, Serialize};
 
+use crate::viewing_key::ViewingKey;
+
 pub static CONFIG_KEY: &[u8] = b"config";
+pub static FOODS_KEY: &[u8] = b"foods";
+pub static ACCESSORIES_KEY: &[u8] = b"accessories";
+pub static BANNED_WORDS_KEY: &[u8] = b"banned_words";
+pub const PREFIX_PETS: &[u8] = b"pets";
+pub const PREFIX_OWNER_PETS: &[u8] = b"owner_pets";
+pub const PREFIX_CHILDREN: &[u8] = b"children";
+pub const PREFIX_HELD_FOODS: &[u8] = b"held_foods";
+pub const PREFIX_LEADERBOARDS: &[u8] = b"leaderboards";
+pub const PREFIX_CARETAKERS: &[u8] = b"caretakers";
+pub const PREFIX_FEEDINGS: &[u8] = b"feedings";
+pub const PREFIX_ILLNESSES: &[u8] = b"illnesses";
+pub const PREFIX_GRAVEYARD: &[u8] = b"graveyard";
+pub const PREFIX_OWNER_GRAVES: &[u8] = b"owner_graves";
+pub const PREFIX_VIEW_KEY: &[u8] = b"viewingkey";
+pub const PREFIX_RECEIVERS: &[u8] = b"receivers";
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-pub struct State {
-    pub owner: HumanAddr,
+pub struct Config {
+    pub admin: HumanAddr,
     pub food_token_code_hash: String,
     pub food_token_addr: HumanAddr,
-    pub full_until: u128,
+    /// Seconds a newly created pet can live without food.
+    pub starvation_window: u64,
+    /// Seconds of satiation bought with a single FOOD unit.
+    pub time_per_food: u64,
+    /// Maximum number of seconds a pet can be fed ahead.
+    pub max_satiation: u64,
+    /// Points of happiness a pet loses every hour.
+    pub happiness_decay: u64,
+    /// Points of cleanliness a pet loses every hour.
+    pub cleanliness_decay: u64,
+    /// Points of energy a pet loses every hour.
+    pub energy_decay: u64,
+    /// Hardcore mode, when disabled dead pets can't be revived.
+    pub revival_enabled: bool,
+    /// Reviving costs this many times the FOOD needed to fill a starvation window.
+    pub revival_multiplier: u64,
+    /// FOOD paid to whoever settles a dead pet, out of `pool`.
+    pub settle_bounty: Uint128,
+    /// FOOD held by the Pet for paying bounties.
+    pub pool: Uint128,
+    pub hibernation_fee: Uint128,
+    /// Maximum number of seconds a pet can hibernate at once.
+    pub max_hibernation: u64,
+    pub breeding_fee: Uint128,
+    /// Token which cures ill pets, `None` until the admin sets it.
+    pub medicine: Option<Medicine>,
+    /// Number of pets ever created, also the id of the latest pet.
+    pub pet_count: u64,
+    pub prng_seed: Vec<u8>,
+    /// Number of random rolls so far, so rolls in the same block differ.
+    pub prng_nonce: u64,
+}
+
+/// State of a single pet.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct State {
+    pub id: u64,
+    pub name: String,
+    pub bio: Option<String>,
+    pub avatar_uri: Option<String>,
+    pub owner: HumanAddr,
+    /// Address the owner offered the pet to, until it accepts the transfer.
+    pub pending_owner: Option<HumanAddr>,
+    pub born_at: u64,
+    pub full_until: u64,
     pub starved: bool,
+    /// Block time of starvation, set once death is recorded.
+    pub died_at: Option<u64>,
+    /// Total amount of food units this pet has eaten, of any kind.
+    pub food_eaten: Uint128,
+    pub feed_count: u64,
+    /// Highest stage reached so far, pets never grow younger.
+    pub stage: LifeStage,
+    /// Number of consecutive on-time feeds, see `STREAK_INTERVAL`.
+    pub streak: u64,
+    pub last_streak_feed: Option<u64>,
+    pub best_streak: u64,
+    /// Start of the current life, the birth or the last revival.
+    pub alive_since: u64,
+    /// Longest life which ended in death, in seconds.
+    pub longest_life: u64,
+    pub happiness: Stat,
+    pub cleanliness: Stat,
+    pub energy: Stat,
+    /// Number of times the pet died and was revived.
+    pub deaths: u64,
+    pub hibernation: Option<Hibernation>,
+    /// Set when the pet falls asleep and cleared by the next feed,
+    /// pets have to be fed between two hibernations.
+    pub hibernated_since_fed: bool,
+    /// Random genes, inherited from the parents for bred pets.
+    pub dna: Binary,
+    /// Decoded from `dna` when the pet is born.
+    pub traits: Traits,
+    /// Ids of the two parents, empty for pets which weren't bred.
+    pub parents: Vec<u64>,
+    pub generation: u64,
+    /// Pet of another owner this pet may be bred with.
+    pub breeding_partner: Option<u64>,
+    /// Listed pets are ranked on the public leaderboards.
+    pub listed: bool,
+    /// FOOD deposited by the owner, used to feed the pet whenever its countdown runs out.
+    pub escrow: Uint128,
+    /// Ill pets burn through satiation twice as fast, until they're treated.
+    pub ill_since: Option<u64>,
+    pub inventory: Vec<Item>,
+}
+
+/// Period during which the starvation countdown is frozen.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Hibernation {
+    pub since: u64,
+    pub until: u64,
+}
+
+impl State {
+    pub fn new(
+        id: u64,
+        name: String,
+        owner: HumanAddr,
+        now: u64,
+        starvation_window: u64,
+        dna: Binary,
+    ) -> Self {
+        let traits = Traits::from_dna(dna.as_slice());
+        State {
+            id,
+            name,
+            bio: None,
+            avatar_uri: None,
+            owner,
+            pending_owner: None,
+            born_at: now,
+            full_until: now + traits.satiation_time(starvation_window),
+            starved: false,
+            died_at: None,
+            food_eaten: Uint128::zero(),
+            feed_count: 0,
+            stage: LifeStage::Egg,
+            streak: 0,
+            last_streak_feed: None,
+            best_streak: 0,
+            alive_since: now,
+            longest_life: 0,
+            happiness: Stat::new(now),
+            cleanliness: Stat::new(now),
+            energy: Stat::new(now),
+            deaths: 0,
+            hibernation: None,
+            hibernated_since_fed: false,
+            dna,
+            traits,
+            parents: vec![],
+            generation: 0,
+            breeding_partner: None,
+            listed: false,
+            escrow: Uint128::zero(),
+            ill_since: None,
+            inventory: vec![],
+        }
+    }
+
+    /// Block time the pet starves at, `full_until` pushed back by the time spent hibernating.
+    /// Satiation left after falling ill lasts half as long.
+    pub fn deadline(&self, now: u64) -> u64 {
+        let full_until = self.satiated_until(now);
+        match self.ill_since {
+            Some(since) if full_until > since => since + (full_until - since) / 2,
+            _ => full_until,
+        }
+    }
+
+    fn satiated_until(&self, now: u64) -> u64 {
+        self.full_until + self.frozen(now)
+    }
+
+    /// Seconds spent hibernating by `now`, since the hibernation was last folded in by `wake`.
+    fn frozen(&self, now: u64) -> u64 {
+        match &self.hibernation {
+            Some(hibernation) => now.min(hibernation.until).saturating_sub(hibernation.since),
+            None => 0,
+        }
+    }
+
+    pub fn is_alive(&self, now: u64) -> bool {
+        !self.starved && self.deadline(now) >= now
+    }
+
+    pub fn is_hibernating(&self, now: u64) -> bool {
+        self.hibernation
+            .as_ref()
+            .map(|hibernation| now < hibernation.until)
+            == Some(true)
+    }
+
+    /// Ends hibernation, keeping the time the pet had left and the stats it had when it fell asleep.
+    pub fn wake(&mut self, now: u64) {
+        let frozen = self.frozen(now);
+        self.full_until += frozen;
+        for stat in &mut [&mut self.happiness, &mut self.cleanliness, &mut self.energy] {
+            stat.updated_at = stat.updated_at.saturating_add(frozen);
+        }
+        self.hibernation = None;
+    }
+
+    pub fn fall_ill(&mut self, now: u64) {
+        self.ill_since = Some(now);
+    }
+
+    /// Cures the pet, keeping the shorter countdown it had while ill.
+    /// Pet must not be hibernating, a finished hibernation is folded into the countdown.
+    /// Returns the time it fell ill.
+    pub fn treat(&mut self, now: u64) -> Option<u64> {
+        let deadline = self.deadline(now);
+        self.wake(now);
+        self.full_until = deadline;
+        self.ill_since.take()
+    }
+
+    /// Seconds left before the pet starves, zero if it's already dead.
+    pub fn seconds_left(&self, now: u64) -> u64 {
+        if self.is_alive(now) {
+            self.deadline(now) - now
+        } else {
+            0
+        }
+    }
+
+    /// Block time of death, also for pets whose death was not recorded yet.
+    pub fn died_at(&self, now: u64) -> Option<u64> {
+        if self.starved {
+            self.died_at
+        } else if self.deadline(now) < now {
+            Some(self.deadline(now))
+        } else {
+            None
+        }
+    }
+
+    /// Seconds the pet has lived, not counting time after its death.
+    pub fn age(&self, now: u64) -> u64 {
+        let until = self.died_at(now).unwrap_or(now);
+        until.saturating_sub(self.born_at)
+    }
+
+    pub fn stage_at(&self, now: u64) -> LifeStage {
+        self.stage
+            .max(LifeStage::from_progress(self.age(now), self.streak))
+    }
+
+    /// Feeds closer than `STREAK_INTERVAL` to the last counted one don't add to the streak,
+    /// so it can't be pumped by feeding many times in a row.
+    pub fn record_feed(&mut self, now: u64) {
+        let counts = match self.last_streak_feed {
+            Some(last) => now >= last + STREAK_INTERVAL,
+            None => true,
+        };
+        if counts {
+            self.streak += 1;
+            self.last_streak_feed = Some(now);
+            self.best_streak = self.best_streak.max(self.streak);
+        }
+    }
+
+    /// Feeds the pet from its escrow, one FOOD every time its countdown would run out,
+    /// as if that happened right on time. Returns the FOOD used.
+    pub fn apply_escrow(&mut self, now: u64, time_per_food: u64) -> u128 {
+        let deadline = self.deadline(now);
+        if self.starved || deadline >= now || time_per_food == 0 {
+            return 0;
+        }
+
+        // Food only lasts half as long for an ill pet
+        let gained = if self.ill_since.is_some() {
+            time_per_food / 2
+        } else {
+            time_per_food
+        };
+        if gained == 0 {
+            return 0;
+        }
+
+        let needed = ((now - deadline) / gained + 1) as u128;
+        let used = needed.min(self.escrow.u128());
+        self.full_until = self
+            .full_until
+            .saturating_add((used as u64).saturating_mul(time_per_food));
+        self.escrow = Uint128(self.escrow.u128() - used);
+        self.food_eaten = Uint128(self.food_eaten.u128().saturating_add(used));
+        used
+    }
+
+    pub fn stat_mut(&mut self, kind: StatKind) -> &mut Stat {
+        match kind {
+            StatKind::Happiness => &mut self.happiness,
+            StatKind::Cleanliness => &mut self.cleanliness,
+            StatKind::Energy => &mut self.energy,
+        }
+    }
+
+    /// Percentage of the usual `kind` decay, after the equipped items' effects.
+    pub fn decay_percent(&self, kind: StatKind) -> u64 {
+        self.inventory
+            .iter()
+            .filter(|item| item.equipped && item.accessory.stat == kind)
+            .fold(100, |percent, item| {
+                percent.saturating_mul(item.accessory.decay_percent) / 100
+            })
+    }
+
+    /// Longest life of the pet so far, including the current one.
+    pub fn lifespan(&self, now: u64) -> u64 {
+        let until = self.died_at(now).unwrap_or(now);
+        let current = until.saturating_sub(self.alive_since);
+        self.longest_life.max(current)
+    }
+
+    /// Time wellbeing stats are measured at, they stop changing while the pet hibernates
+    /// and once it dies.
+    pub fn stats_time(&self, now: u64) -> u64 {
+        let now = self.died_at(now).unwrap_or(now);
+        now - self.frozen(now)
+    }
+
+    /// Brings a dead pet back with a fresh countdown and full stats.
+    /// Stage is kept, but the feeding streak starts over.
+    pub fn revive(&mut self, now: u64, starvation_window: u64) {
+        self.full_until = now + self.traits.satiation_time(starvation_window);
+        self.starved = false;
+        self.died_at = None;
+        self.ill_since = None;
+        self.alive_since = now;
+        self.deaths += 1;
+        self.streak = 0;
+        self.last_streak_feed = None;
+        self.happiness = Stat::new(now);
+        self.cleanliness = Stat::new(now);
+        self.energy = Stat::new(now);
+    }
+
+    pub fn record_death(&mut self, now: u64) {
+        if !self.starved {
+            let died_at = self.deadline(now);
+            self.wake(now);
+            self.starved = true;
+            self.died_at = Some(died_at);
+            let life = died_at.saturating_sub(self.alive_since);
+            self.longest_life = self.longest_life.max(life);
+        }
+    }
+}
+
+// Traits
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Species {
+    Cat,
+    Dog,
+    Rabbit,
+    Turtle,
+    Dragon,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Color {
+    Red,
+    Orange,
+    Yellow,
+    Green,
+    Blue,
+    Purple,
+    Black,
+    White,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Temperament {
+    Calm,
+    Playful,
+    Grumpy,
+    Shy,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Traits {
+    pub species: Species,
+    pub color: Color,
+    pub temperament: Temperament,
+    /// Percentage of the normal rate at which the pet gets hungry, from 80 to 120.
+    pub metabolism: u64,
+}
+
+impl Traits {
+    const SPECIES: [Species; 5] = [
+        Species::Cat,
+        Species::Dog,
+        Species::Rabbit,
+        Species::Turtle,
+        Species::Dragon,
+    ];
+    const COLORS: [Color; 8] = [
+        Color::Red,
+        Color::Orange,
+        Color::Yellow,
+        Color::Green,
+        Color::Blue,
+        Color::Purple,
+        Color::Black,
+        Color::White,
+    ];
+    const TEMPERAMENTS: [Temperament; 4] = [
+        Temperament::Calm,
+        Temperament::Playful,
+        Temperament::Grumpy,
+        Temperament::Shy,
+    ];
+
+    /// Each trait is decoded from its own gene, missing genes count as zero.
+    pub fn from_dna(dna: &[u8]) -> Self {
+        let gene = |i: usize| dna.get(i).copied().unwrap_or_default() as usize;
+        Traits {
+            species: Self::SPECIES[gene(0) % Self::SPECIES.len()],
+            color: Self::COLORS[gene(1) % Self::COLORS.len()],
+            temperament: Self::TEMPERAMENTS[gene(2) % Self::TEMPERAMENTS.len()],
+            metabolism: 80 + (gene(3) % 41) as u64,
+        }
+    }
+
+    /// Scales a satiation time of an average pet, like the starvation window, the time
+    /// gained per FOOD or the satiation cap. Pets with a fast metabolism starve sooner
+    /// and get less out of every meal, slow ones last longer.
+    pub fn satiation_time(&self, base: u64) -> u64 {
+        base.saturating_mul(100) / self.metabolism
+    }
+}
+
+// Wellbeing
+
+pub const MAX_STAT: u64 = 100;
+
+/// Wellbeing stat which decays over time, from `MAX_STAT` down to zero.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Stat {
+    pub value: u64,
+    pub updated_at: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum StatKind {
+    Happiness,
+    Cleanliness,
+    Energy,
+}
+
+impl Stat {
+    pub fn new(now: u64) -> Self {
+        Stat {
+            value: MAX_STAT,
+            updated_at: now,
+        }
+    }
+
+    /// Value at `now`, after losing `decay` points every hour since the last update.
+    pub fn value_at(&self, now: u64, decay: u64) -> u64 {
+        let elapsed = now.saturating_sub(self.updated_at);
+        self.value
+            .saturating_sub(elapsed.saturating_mul(decay) / HOUR)
+    }
+
+    pub fn set(&mut self, now: u64, value: u64) {
+        self.value = value.min(MAX_STAT);
+        self.updated_at = now;
+    }
+}
+
+// Life stages
+
+/// Minimum number of seconds between two feeds counted in the streak.
+pub const STREAK_INTERVAL: u64 = 60 * 60;
+
+const HOUR: u64 = 60 * 60;
+const DAY: u64 = 24 * HOUR;
+
+#[derive(
+    Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum LifeStage {
+    Egg,
+    Baby,
+    Child,
+    Adult,
+    Elder,
+}
+
+impl LifeStage {
+    /// Minimum age (in seconds) and feeding streak required for each stage, oldest first.
+    const REQUIREMENTS: [(LifeStage, u64, u64); 4] = [
+        (LifeStage::Elder, 14 * DAY, 30),
+        (LifeStage::Adult, 7 * DAY, 15),
+        (LifeStage::Child, 2 * DAY, 5),
+        (LifeStage::Baby, HOUR, 0),
+    ];
+
+    pub fn from_progress(age: u64, streak: u64) -> Self {
+        Self::REQUIREMENTS
+            .iter()
+            .find(|(_, min_age, min_streak)| age >= *min_age && streak >= *min_streak)
+            .map(|(stage, _, _)| *stage)
+            .unwrap_or(LifeStage::Egg)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LifeStage::Egg => "egg",
+            LifeStage::Baby => "baby",
+            LifeStage::Child => "child",
+            LifeStage::Adult => "adult",
+            LifeStage::Elder => "elder",
+        }
+    }
+}
+
+impl Config {
+    /// FOOD token the Pet was created with, always accepted and always burned.
+    pub fn primary_food(&self) -> Food {
+        Food {
+            contract: self.food_token_addr.clone(),
+            code_hash: self.food_token_code_hash.clone(),
+            nutrition: self.time_per_food,
+            burn: true,
+        }
+    }
 }
 
-pub fn config<S: Storage>(storage: &mut S) -> Singleton<S, State> {
+pub fn config<S: Storage>(storage: &mut S) -> Singleton<S, Config> {
     singleton(storage, CONFIG_KEY)
 }
 
-pub fn config_read<S: Storage>(storage: &S) -> ReadonlySingleton<S, State> {
+pub fn config_read<S: Storage>(storage: &S) -> ReadonlySingleton<S, Config> {
     singleton_read(storage, CONFIG_KEY)
 }
+
+// Foods
+
+/// SNIP-20 token the Pet accepts as food.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Food {
+    pub contract: HumanAddr,
+    pub code_hash: String,
+    /// Seconds of satiation bought with a single unit.
+    pub nutrition: u64,
+    /// Whether eaten tokens are burned, or kept in the Pet's balance.
+    pub burn: bool,
+}
+
+/// Returns the amount of a food which was eaten but kept in the Pet's balance.
+pub fn read_held_food<S: ReadonlyStorage>(storage: &S, contract: &HumanAddr) -> StdResult<Uint128> {
+    let held = bucket_read(PREFIX_HELD_FOODS, storage).may_load(contract.0.as_bytes())?;
+    Ok(held.unwrap_or_default())
+}
+
+pub fn write_held_food<S: Storage>(
+    storage: &mut S,
+    contract: &HumanAddr,
+    amount: Uint128,
+) -> StdResult<()> {
+    bucket(PREFIX_HELD_FOODS, storage).save(contract.0.as_bytes(), &amount)
+}
+
+/// SNIP-20 token used to treat ill pets.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Medicine {
+    pub contract: HumanAddr,
+    pub code_hash: String,
+}
+
+/// Returns the whitelisted foods, not including the primary FOOD.
+pub fn read_foods<S: ReadonlyStorage>(storage: &S) -> StdResult<Vec<Food>> {
+    let foods = singleton_read(storage, FOODS_KEY).may_load()?;
+    Ok(foods.unwrap_or_default())
+}
+
+pub fn write_foods<S: Storage>(storage: &mut S, foods: &[Food]) -> StdResult<()> {
+    singleton(storage, FOODS_KEY).save(&foods.to_vec())
+}
+
+pub fn find_food<S: ReadonlyStorage>(storage: &S, contract: &HumanAddr) -> StdResult<Option<Food>> {
+    let config_state: Config = singleton_read(storage, CONFIG_KEY).load()?;
+    if &config_state.food_token_addr == contract {
+        return Ok(Some(config_state.primary_food()));
+    }
+
+    let food = read_foods(storage)?
+        .into_iter()
+        .find(|food| &food.contract == contract);
+    Ok(food)
+}
+
+// Accessories
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenStandard {
+    Snip20,
+    Snip721,
+}
+
+pub const MAX_DECAY_PERCENT: u64 = 200;
+
+/// Token which pets can wear, slowing down (or speeding up) the decay of a stat.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Accessory {
+    pub contract: HumanAddr,
+    pub code_hash: String,
+    pub standard: TokenStandard,
+    pub stat: StatKind,
+    /// Percentage of the usual decay while it's equipped, at most `MAX_DECAY_PERCENT`.
+    pub decay_percent: u64,
+}
+
+/// Accessory held in a pet's inventory. Units of a SNIP-20 accessory are kept together.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Item {
+    pub accessory: Accessory,
+    pub token_id: Option<String>,
+    pub amount: Uint128,
+    pub equipped: bool,
+}
+
+pub fn read_accessories<S: ReadonlyStorage>(storage: &S) -> StdResult<Vec<Accessory>> {
+    let accessories = singleton_read(storage, ACCESSORIES_KEY).may_load()?;
+    Ok(accessories.unwrap_or_default())
+}
+
+pub fn write_accessories<S: Storage>(storage: &mut S, accessories: &[Accessory]) -> StdResult<()> {
+    singleton(storage, ACCESSORIES_KEY).save(&accessories.to_vec())
+}
+
+pub fn find_accessory<S: ReadonlyStorage>(
+    storage: &S,
+    contract: &HumanAddr,
+) -> StdResult<Option<Accessory>> {
+    let accessory = read_accessories(storage)?
+        .into_iter()
+        .find(|accessory| &accessory.contract == contract);
+    Ok(accessory)
+}
+
+/// Returns the lowercase words pet names and bios can't contain.
+pub fn read_banned_words<S: ReadonlyStorage>(storage: &S) -> StdResult<Vec<String>> {
+    let words = singleton_read(storage, BANNED_WORDS_KEY).may_load()?;
+    Ok(words.unwrap_or_default())
+}
+
+pub fn write_banned_words<S: Storage>(storage: &mut S, words: &[String]) -> StdResult<()> {
+    let words: Vec<String> = words.iter().map(|word| word.to_lowercase()).collect();
+    singleton(storage, BANNED_WORDS_KEY).save(&words)
+}
+
+// Leaderboards
+
+/// Number of pets ranked on each board, so reading or updating one stays cheap.
+pub const LEADERBOARD_SIZE: usize = 100;
+
+/// Score of a pet on a board, as of its last update.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BoardEntry {
+    pub score: Uint128,
+    pub pet_id: u64,
+}
+
+/// Returns the entries of a board from the highest score, equal scores are ordered by pet id.
+pub fn read_leaderboard<S: ReadonlyStorage>(
+    storage: &S,
+    board: &[u8],
+) -> StdResult<Vec<BoardEntry>> {
+    let entries = bucket_read(PREFIX_LEADERBOARDS, storage).may_load(board)?;
+    Ok(entries.unwrap_or_default())
+}
+
+/// Moves the pet to the rank of `score`, or takes it off the board if `score` is `None`.
+/// Only the best `LEADERBOARD_SIZE` pets are kept, a pet pushed off comes back
+/// with the first update which ranks it high enough.
+pub fn update_leaderboard<S: Storage>(
+    storage: &mut S,
+    board: &[u8],
+    pet_id: u64,
+    score: Option<Uint128>,
+) -> StdResult<()> {
+    let mut entries = read_leaderboard(storage, board)?;
+    entries.retain(|entry| entry.pet_id != pet_id);
+    if let Some(score) = score {
+        let rank = entries
+            .iter()
+            .position(|entry| {
+                entry.score < score || (entry.score == score && entry.pet_id > pet_id)
+            })
+            .unwrap_or(entries.len());
+        if rank < LEADERBOARD_SIZE {
+            entries.insert(rank, BoardEntry { score, pet_id });
+            entries.truncate(LEADERBOARD_SIZE);
+        }
+    }
+    bucket(PREFIX_LEADERBOARDS, storage).save(board, &entries)
+}
+
+// Pets
+
+pub fn pets<S: Storage>(storage: &mut S) -> Bucket<S, State> {
+    bucket(PREFIX_PETS, storage)
+}
+
+pub fn pets_read<S: ReadonlyStorage>(storage: &S) -> ReadonlyBucket<S, State> {
+    bucket_read(PREFIX_PETS, storage)
+}
+
+pub fn load_pet<S: ReadonlyStorage>(storage: &S, pet_id: u64) -> StdResult<State> {
+    pets_read(storage)
+        .may_load(&pet_id.to_be_bytes())?
+        .ok_or_else(|| StdError::generic_err(format!("Pet {} does not exist", pet_id)))
+}
+
+pub fn save_pet<S: Storage>(storage: &mut S, pet: &State) -> StdResult<()> {
+    pets(storage).save(&pet.id.to_be_bytes(), pet)
+}
+
+// Lineage
+
+pub fn read_children<S: ReadonlyStorage>(storage: &S, pet_id: u64) -> StdResult<Vec<u64>> {
+    let children = bucket_read(PREFIX_CHILDREN, storage).may_load(&pet_id.to_be_bytes())?;
+    Ok(children.unwrap_or_default())
+}
+
+pub fn add_child<S: Storage>(storage: &mut S, parent_id: u64, child_id: u64) -> StdResult<()> {
+    let mut children = read_children(storage, parent_id)?;
+    children.push(child_id);
+    bucket(PREFIX_CHILDREN, storage).save(&parent_id.to_be_bytes(), &children)
+}
+
+// Owner index
+
+/// Returns ids of all pets owned by `owner`, in order of creation.
+pub fn read_owner_pets<S: ReadonlyStorage>(
+    storage: &S,
+    owner: &CanonicalAddr,
+) -> StdResult<Vec<u64>> {
+    let owner_pets = bucket_read(PREFIX_OWNER_PETS, storage).may_load(owner.as_slice())?;
+    Ok(owner_pets.unwrap_or_default())
+}
+
+pub fn add_owner_pet<S: Storage>(
+    storage: &mut S,
+    owner: &CanonicalAddr,
+    pet_id: u64,
+) -> StdResult<()> {
+    let mut owner_pets = read_owner_pets(storage, owner)?;
+    owner_pets.push(pet_id);
+    bucket(PREFIX_OWNER_PETS, storage).save(owner.as_slice(), &owner_pets)
+}
+
+pub fn remove_owner_pet<S: Storage>(
+    storage: &mut S,
+    owner: &CanonicalAddr,
+    pet_id: u64,
+) -> StdResult<()> {
+    let mut owner_pets = read_owner_pets(storage, owner)?;
+    owner_pets.retain(|id| *id != pet_id);
+    bucket(PREFIX_OWNER_PETS, storage).save(owner.as_slice(), &owner_pets)
+}
+
+// Caretakers
+
+/// Someone allowed to feed a pet on behalf of its owner.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Caretaker {
+    pub address: HumanAddr,
+    /// Block time after which the caretaker can no longer feed the pet.
+    pub expiration: Option<u64>,
+}
+
+impl Caretaker {
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.expiration.map(|ex| ex < now) == Some(true)
+    }
+}
+
+pub fn read_caretakers<S: ReadonlyStorage>(storage: &S, pet_id: u64) -> StdResult<Vec<Caretaker>> {
+    let caretakers = bucket_read(PREFIX_CARETAKERS, storage).may_load(&pet_id.to_be_bytes())?;
+    Ok(caretakers.unwrap_or_default())
+}
+
+pub fn write_caretakers<S: Storage>(
+    storage: &mut S,
+    pet_id: u64,
+    caretakers: &[Caretaker],
+) -> StdResult<()> {
+    bucket(PREFIX_CARETAKERS, storage).save(&pet_id.to_be_bytes(), &caretakers.to_vec())
+}
+
+// Feeding history
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
+pub struct FeedEvent {
+    pub time: u64,
+    pub feeder: HumanAddr,
+    pub food: HumanAddr,
+    pub amount: Uint128,
+    /// Food sent to a dead pet is returned instead of eaten.
+    pub refunded: bool,
+}
+
+impl FeedEvent {
+    pub fn into_stored<A: Api>(self, api: &A) -> StdResult<StoredFeedEvent> {
+        let event = StoredFeedEvent {
+            time: self.time,
+            feeder: api.canonical_address(&self.feeder)?,
+            food: api.canonical_address(&self.food)?,
+            amount: self.amount.u128(),
+            refunded: self.refunded,
+        };
+        Ok(event)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StoredFeedEvent {
+    pub time: u64,
+    pub feeder: CanonicalAddr,
+    pub food: CanonicalAddr,
+    pub amount: u128,
+    pub refunded: bool,
+}
+
+impl StoredFeedEvent {
+    pub fn into_humanized<A: Api>(self, api: &A) -> StdResult<FeedEvent> {
+        let event = FeedEvent {
+            time: self.time,
+            feeder: api.human_address(&self.feeder)?,
+            food: api.human_address(&self.food)?,
+            amount: Uint128(self.amount),
+            refunded: self.refunded,
+        };
+        Ok(event)
+    }
+}
+
+pub fn store_feed_event<S: Storage>(
+    store: &mut S,
+    pet_id: u64,
+    event: &StoredFeedEvent,
+) -> StdResult<()> {
+    let mut store = PrefixedStorage::multilevel(&[PREFIX_FEEDINGS, &pet_id.to_be_bytes()], store);
+    let mut store = AppendStoreMut::attach_or_create(&mut store)?;
+    store.push(event)
+}
+
+pub fn get_feed_events<A: Api, S: ReadonlyStorage>(
+    api: &A,
+    storage: &S,
+    pet_id: u64,
+    page: u32,
+    page_size: u32,
+) -> StdResult<Vec<FeedEvent>> {
+    let store =
+        ReadonlyPrefixedStorage::multilevel(&[PREFIX_FEEDINGS, &pet_id.to_be_bytes()], storage);
+
+    // If the pet was never fed, return an empty history.
+    let store = if let Some(result) = AppendStore::<StoredFeedEvent, _>::attach(&store) {
+        result?
+    } else {
+        return Ok(vec![]);
+    };
+
+    // Latest events come first
+    store
+        .iter()
+        .rev()
+        .skip((page * page_size) as _)
+        .take(page_size as _)
+        .map(|event| event.and_then(|event| event.into_humanized(api)))
+        .collect()
+}
+
+// Illnesses
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum IllnessOutcome {
+    Treated,
+    Died,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Illness {
+    pub since: u64,
+    pub until: u64,
+    pub outcome: IllnessOutcome,
+}
+
+pub fn store_illness<S: Storage>(store: &mut S, pet_id: u64, illness: &Illness) -> StdResult<()> {
+    let mut store = PrefixedStorage::multilevel(&[PREFIX_ILLNESSES, &pet_id.to_be_bytes()], store);
+    let mut store = AppendStoreMut::attach_or_create(&mut store)?;
+    store.push(illness)
+}
+
+/// Returns a page of the pet's past illnesses, latest first.
+pub fn get_illnesses<S: ReadonlyStorage>(
+    storage: &S,
+    pet_id: u64,
+    page: u32,
+    page_size: u32,
+) -> StdResult<Vec<Illness>> {
+    let store =
+        ReadonlyPrefixedStorage::multilevel(&[PREFIX_ILLNESSES, &pet_id.to_be_bytes()], storage);
+
+    // Pet was never ill
+    let store = if let Some(result) = AppendStore::<Illness, _>::attach(&store) {
+        result?
+    } else {
+        return Ok(vec![]);
+    };
+
+    store
+        .iter()
+        .rev()
+        .skip((page * page_size) as _)
+        .take(page_size as _)
+        .collect()
+}
+
+// Graveyard
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DeathCause {
+    Starvation,
+    /// Starved while ill, and never got treated.
+    Illness,
+}
+
+/// Permanent record of a pet's life, written every time it dies.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Memorial {
+    pub pet_id: u64,
+    pub name: String,
+    pub owner: HumanAddr,
+    pub born_at: u64,
+    pub died_at: u64,
+    /// Seconds between the start of this life and the death.
+    pub lifespan: u64,
+    pub cause: DeathCause,
+    pub stage: LifeStage,
+    pub happiness: u64,
+    pub cleanliness: u64,
+    pub energy: u64,
+    pub food_eaten: Uint128,
+    pub feed_count: u64,
+    pub generation: u64,
+}
+
+/// Memorials are kept in one list, each owner has a list of indexes into it.
+pub fn store_memorial<S: Storage>(
+    store: &mut S,
+    owner: &CanonicalAddr,
+    memorial: &Memorial,
+) -> StdResult<()> {
+    let index = {
+        let mut store = PrefixedStorage::new(PREFIX_GRAVEYARD, store);
+        let mut store = AppendStoreMut::attach_or_create(&mut store)?;
+        store.push(memorial)?;
+        store.len() - 1
+    };
+
+    let mut store = PrefixedStorage::multilevel(&[PREFIX_OWNER_GRAVES, owner.as_slice()], store);
+    let mut store = AppendStoreMut::attach_or_create(&mut store)?;
+    store.push(&index)
+}
+
+/// Returns a page of memorials, latest deaths first.
+pub fn get_memorials<S: ReadonlyStorage>(
+    storage: &S,
+    page: u32,
+    page_size: u32,
+) -> StdResult<Vec<Memorial>> {
+    let store = ReadonlyPrefixedStorage::new(PREFIX_GRAVEYARD, storage);
+
+    // Nobody's pet has died yet
+    let store = if let Some(result) = AppendStore::<Memorial, _>::attach(&store) {
+        result?
+    } else {
+        return Ok(vec![]);
+    };
+
+    store
+        .iter()
+        .rev()
+        .skip((page * page_size) as _)
+        .take(page_size as _)
+        .collect()
+}
+
+/// Returns a page of the memorials of pets which died while owned by `owner`, latest first.
+pub fn get_owner_memorials<S: ReadonlyStorage>(
+    storage: &S,
+    owner: &CanonicalAddr,
+    page: u32,
+    page_size: u32,
+) -> StdResult<Vec<Memorial>> {
+    let index_store =
+        ReadonlyPrefixedStorage::multilevel(&[PREFIX_OWNER_GRAVES, owner.as_slice()], storage);
+    let index_store = if let Some(result) = AppendStore::<u32, _>::attach(&index_store) {
+        result?
+    } else {
+        return Ok(vec![]);
+    };
+
+    let store = ReadonlyPrefixedStorage::new(PREFIX_GRAVEYARD, storage);
+    let store = AppendStore::<Memorial, _>::attach(&store)
+        .ok_or_else(|| StdError::generic_err("Graveyard is missing memorials"))??;
+
+    index_store
+        .iter()
+        .rev()
+        .skip((page * page_size) as _)
+        .take(page_size as _)
+        .map(|index| index.and_then(|index| store.get_at(index)))
+        .collect()
+}
+
+// Viewing Keys
+
+pub fn write_viewing_key<S: Storage>(store: &mut S, owner: &CanonicalAddr, key: &ViewingKey) {
+    let mut key_store = PrefixedStorage::new(PREFIX_VIEW_KEY, store);
+    key_store.set(owner.as_slice(), &key.to_hashed());
+}
+
+pub fn read_viewing_key<S: Storage>(store: &S, owner: &CanonicalAddr) -> Option<Vec<u8>> {
+    let key_store = ReadonlyPrefixedStorage::new(PREFIX_VIEW_KEY, store);
+    key_store.get(owner.as_slice())
+}
+
+// NFT receivers
+
+pub fn get_receiver_hash<S: ReadonlyStorage>(
+    store: &S,
+    account: &HumanAddr,
+) -> Option<StdResult<String>> {
+    let store = ReadonlyPrefixedStorage::new(PREFIX_RECEIVERS, store);
+    store.get(account.as_str().as_bytes()).map(|data| {
+        String::from_utf8(data)
+            .map_err(|_err| StdError::invalid_utf8("stored code hash was not a valid String"))
+    })
+}
+
+pub fn set_receiver_hash<S: Storage>(store: &mut S, account: &HumanAddr, code_hash: String) {
+    let mut store = PrefixedStorage::new(PREFIX_RECEIVERS, store);
+    store.set(account.as_str().as_bytes(), code_hash.as_bytes());
+}